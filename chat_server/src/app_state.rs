@@ -6,10 +6,12 @@ use r2d2_redis::RedisConnectionManager;
 use sqlx::PgPool;
 use tokio::sync::{broadcast};
 use crate::config::AppConfig;
+use crate::filter::MessageFilterChain;
 use crate::mutation::MutationRoot;
 use crate::notification::Notification;
+use crate::preview::{HttpFetcher, LinkPreviewService};
 use crate::query::QueryRoot;
-use crate::repository::{ChatRepository, MessageRepository, UserRepository};
+use crate::repository::{AccountRepository, AdminRepository, ChatRepository, ContactRepository, MessageRepository, PollRepository, ReportRepository, ScheduleRepository, StickerRepository, UserRepository};
 use crate::subscription::SubscriptionRoot;
 use crate::utils::{DecodingKey, EncodingKey};
 
@@ -40,10 +42,23 @@ impl AppState {
 
         Self {
             inner: Arc::new(AppStateInner {
-                config,
                 user_repo: UserRepository::new(pool.clone(), rdb_pool.clone()),
-                chat_repo: ChatRepository::new(pool.clone()),
+                chat_repo: ChatRepository::new(pool.clone(), config.chat.require_contact_for_private_chat),
                 message_repo: MessageRepository::new(pool.clone()),
+                contact_repo: ContactRepository::new(pool.clone()),
+                report_repo: ReportRepository::new(pool.clone()),
+                account_repo: AccountRepository::new(pool.clone()),
+                admin_repo: AdminRepository::new(pool.clone()),
+                schedule_repo: ScheduleRepository::new(pool.clone()),
+                poll_repo: PollRepository::new(pool.clone()),
+                sticker_repo: StickerRepository::new(pool.clone()),
+                message_filters: MessageFilterChain::from_config(&config.message_filter, pool.clone()),
+                link_previews: LinkPreviewService::new(
+                    Arc::new(HttpFetcher::new(&config.link_preview)),
+                    rdb_pool.clone(),
+                    &config.link_preview,
+                ),
+                config,
                 pool,
                 rdb_pool,
                 dk,
@@ -69,6 +84,15 @@ pub(crate) struct AppStateInner {
     pub(crate) user_repo: UserRepository,
     pub(crate) chat_repo: ChatRepository,
     pub(crate) message_repo: MessageRepository,
+    pub(crate) contact_repo: ContactRepository,
+    pub(crate) report_repo: ReportRepository,
+    pub(crate) account_repo: AccountRepository,
+    pub(crate) admin_repo: AdminRepository,
+    pub(crate) schedule_repo: ScheduleRepository,
+    pub(crate) poll_repo: PollRepository,
+    pub(crate) sticker_repo: StickerRepository,
+    pub(crate) message_filters: MessageFilterChain,
+    pub(crate) link_previews: LinkPreviewService,
     pub(crate) dk: DecodingKey,
     pub(crate) ek: EncodingKey,
     pub(crate) sender: Arc<broadcast::Sender<Notification>>,
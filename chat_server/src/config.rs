@@ -1,6 +1,7 @@
 use std::ops::Deref;
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
+use crate::filter::KeywordAction;
 use tokio::sync::OnceCell;
 
 #[derive(Clone)]
@@ -20,6 +21,18 @@ impl Deref for AppConfig {
 pub(crate) struct AppConfigInner {
     pub(crate) server: ServerConfig,
     pub(crate) jwt: JwtConfig,
+    #[serde(default)]
+    pub(crate) message: MessageConfig,
+    #[serde(default)]
+    pub(crate) chat: ChatConfig,
+    #[serde(default)]
+    pub(crate) account: AccountConfig,
+    #[serde(default)]
+    pub(crate) message_filter: MessageFilterConfig,
+    #[serde(default)]
+    pub(crate) link_preview: LinkPreviewConfig,
+    #[serde(default)]
+    pub(crate) storage: StorageConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,6 +50,94 @@ pub(crate) struct JwtConfig {
     pub(crate) period_seconds: u64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MessageConfig {
+    /// Page size of every connection, chats and users included.
+    pub(crate) default_page_size: i32,
+    pub(crate) max_page_size: i32,
+}
+
+impl Default for MessageConfig {
+    fn default() -> Self {
+        Self {
+            default_page_size: 20,
+            max_page_size: 100,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct ChatConfig {
+    /// Only allow private chats between users who are contacts of each other.
+    pub(crate) require_contact_for_private_chat: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct AccountConfig {
+    /// Days between requesting the deletion of an account and anonymizing it.
+    pub(crate) deletion_grace_days: i64,
+    /// Hours a finished data export can be downloaded.
+    pub(crate) export_ttl_hours: i64,
+}
+
+impl Default for AccountConfig {
+    fn default() -> Self {
+        Self {
+            deletion_grace_days: 14,
+            export_ttl_hours: 24,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MessageFilterConfig {
+    /// Max chars of a text message.
+    pub(crate) max_length: usize,
+    /// Words masked or rejected in text messages, ignoring case.
+    pub(crate) blocked_keywords: Vec<String>,
+    pub(crate) keyword_action: KeywordAction,
+}
+
+impl Default for MessageFilterConfig {
+    fn default() -> Self {
+        Self {
+            max_length: 4000,
+            blocked_keywords: vec![],
+            keyword_action: KeywordAction::Mask,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct LinkPreviewConfig {
+    /// Fetch previews of the first link in text messages.
+    pub(crate) enabled: bool,
+    /// Time limit of fetching a page, redirects included.
+    pub(crate) timeout_ms: u64,
+    /// Bytes of a page read at most, the rest is ignored.
+    pub(crate) max_body_bytes: usize,
+    pub(crate) max_redirects: usize,
+    pub(crate) cache_ttl_secs: usize,
+}
+
+impl Default for LinkPreviewConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timeout_ms: 5000,
+            max_body_bytes: 512 * 1024,
+            max_redirects: 3,
+            cache_ttl_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct StorageConfig {
+    /// Base url of the uploaded files, avatars and sticker images must point below it. Nothing is accepted when empty.
+    pub(crate) public_url: String,
+}
+
 impl AppConfig {
     pub(crate) fn load() -> Self {
         #[cfg(not(test))]
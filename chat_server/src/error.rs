@@ -1,3 +1,5 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
 use async_graphql::{Error, ErrorExtensions};
 use axum::http::{Response, StatusCode};
 use axum::response::IntoResponse;
@@ -55,6 +57,56 @@ pub(crate) enum AppError {
 
     #[error("Unauthorized")]
     Unauthorized,
+
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+
+    #[error("DataLoader error: {0}")]
+    LoaderError(String),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Contact error: {0}")]
+    ContactError(String),
+
+    #[error("Friend request not found")]
+    FriendRequestNotFound,
+
+    #[error("Data export not found")]
+    DataExportNotFound,
+
+    #[error("Scheduled message not found")]
+    ScheduledMessageNotFound,
+
+    #[error("Sticker not found")]
+    StickerNotFound,
+
+    #[error("Forbidden")]
+    Forbidden,
+
+    #[error("Account suspended until {until}")]
+    AccountSuspended {
+        until: DateTime<Utc>,
+        reason: Option<String>,
+    },
+
+    #[error("Message rejected: {0}")]
+    MessageRejected(String),
+
+    #[error("Link preview error: {0}")]
+    LinkPreviewError(String),
+
+    #[error("Account banned")]
+    AccountBanned {
+        reason: Option<String>,
+    },
+}
+
+impl From<Arc<AppError>> for AppError {
+    fn from(err: Arc<AppError>) -> Self {
+        Self::LoaderError(err.to_string())
+    }
 }
 
 impl IntoResponse for AppError {
@@ -77,6 +129,19 @@ impl IntoResponse for AppError {
             Self::NotificationError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::SerdeJsonError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::InvalidCursor(_) => StatusCode::BAD_REQUEST,
+            Self::LoaderError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            Self::ContactError(_) => StatusCode::BAD_REQUEST,
+            Self::FriendRequestNotFound => StatusCode::NOT_FOUND,
+            Self::DataExportNotFound => StatusCode::NOT_FOUND,
+            Self::ScheduledMessageNotFound => StatusCode::NOT_FOUND,
+            Self::StickerNotFound => StatusCode::NOT_FOUND,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::MessageRejected(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::LinkPreviewError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::AccountSuspended { .. } => StatusCode::LOCKED,
+            Self::AccountBanned { .. } => StatusCode::LOCKED,
         };
 
         (status, self.to_string()).into_response()
@@ -106,6 +171,33 @@ impl ErrorExtensions for AppError {
             AppError::NotificationError(_) => {}
             AppError::SerdeJsonError(_) => {}
             AppError::Unauthorized => {}
+            AppError::InvalidCursor(_) => {}
+            AppError::LoaderError(_) => {}
+            AppError::InvalidInput(_) => {}
+            AppError::ContactError(_) => {}
+            AppError::FriendRequestNotFound => {}
+            AppError::DataExportNotFound => {}
+            AppError::ScheduledMessageNotFound => {}
+            AppError::StickerNotFound => {}
+            AppError::Forbidden => {
+                e.set("code", StatusCode::FORBIDDEN.as_u16())
+            }
+            AppError::MessageRejected(_) => {}
+            AppError::LinkPreviewError(_) => {}
+            AppError::AccountSuspended { until, reason } => {
+                e.set("code", StatusCode::LOCKED.as_u16());
+                e.set("suspendedUntil", until.to_rfc3339());
+                if let Some(reason) = reason {
+                    e.set("reason", reason.as_str());
+                }
+            }
+            AppError::AccountBanned { reason } => {
+                e.set("code", StatusCode::LOCKED.as_u16());
+                e.set("banned", true);
+                if let Some(reason) = reason {
+                    e.set("reason", reason.as_str());
+                }
+            }
         })
     }
 }
@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use crate::error::AppError;
+use crate::filter::{MessageFilter, OutgoingMessage};
+use crate::models::MessageType;
+
+const MASK: char = '*';
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum KeywordAction {
+    /// Replace every char of the keyword with `*`.
+    #[default]
+    Mask,
+    Reject,
+}
+
+/// Masks or rejects text messages containing any of the blocked keywords, ignoring case.
+pub(crate) struct KeywordFilter {
+    keywords: Vec<Vec<char>>,
+    action: KeywordAction,
+}
+
+impl KeywordFilter {
+    pub(crate) fn new(keywords: Vec<String>, action: KeywordAction) -> Self {
+        let keywords = keywords
+            .iter()
+            .map(|k| lowercase_chars(k.trim()))
+            .filter(|k| !k.is_empty())
+            .collect();
+
+        Self {
+            keywords,
+            action,
+        }
+    }
+
+    /// The content with the keywords masked, or `None` if it contains none of them.
+    fn mask(&self, content: &str) -> Option<String> {
+        let mut chars: Vec<char> = content.chars().collect();
+        let lowered = lowercase_chars(content);
+        let mut found = false;
+
+        for keyword in &self.keywords {
+            let mut i = 0;
+            while i + keyword.len() <= lowered.len() {
+                if lowered[i..i + keyword.len()] == keyword[..] {
+                    chars[i..i + keyword.len()].fill(MASK);
+                    found = true;
+                    i += keyword.len();
+                } else {
+                    i += 1;
+                }
+            }
+        }
+
+        found.then(|| chars.into_iter().collect())
+    }
+}
+
+#[async_trait]
+impl MessageFilter for KeywordFilter {
+    async fn check(&self, message: &mut OutgoingMessage) -> Result<(), AppError> {
+        if message.r#type != MessageType::Text {
+            return Ok(());
+        }
+
+        if let Some(masked) = self.mask(&message.content) {
+            match self.action {
+                KeywordAction::Mask => message.content = masked,
+                KeywordAction::Reject => {
+                    return Err(AppError::MessageRejected("Message contains blocked words".to_string()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// One lowercase char per char of the input, so positions match the original text.
+fn lowercase_chars(s: &str) -> Vec<char> {
+    s.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyword_filter_mask_should_work() {
+        let filter = KeywordFilter::new(vec!["spam".to_string(), "广告".to_string()], KeywordAction::Mask);
+
+        assert_eq!(filter.mask("Buy SPAM now, 看广告"), Some("Buy **** now, 看**".to_string()));
+        assert_eq!(filter.mask("hello"), None);
+    }
+}
@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+use crate::error::AppError;
+use crate::filter::{MessageFilter, OutgoingMessage};
+use crate::models::MessageType;
+
+/// Rejects empty text messages and the ones longer than `max_length` chars.
+pub(crate) struct LengthFilter {
+    max_length: usize,
+}
+
+impl LengthFilter {
+    pub(crate) fn new(max_length: usize) -> Self {
+        Self {
+            max_length,
+        }
+    }
+}
+
+#[async_trait]
+impl MessageFilter for LengthFilter {
+    async fn check(&self, message: &mut OutgoingMessage) -> Result<(), AppError> {
+        if message.r#type != MessageType::Text {
+            return Ok(());
+        }
+
+        if message.content.trim().is_empty() {
+            return Err(AppError::MessageRejected("Message can not be empty".to_string()));
+        }
+
+        if message.content.chars().count() > self.max_length {
+            return Err(AppError::MessageRejected(format!("Message is longer than {} characters", self.max_length)));
+        }
+
+        Ok(())
+    }
+}
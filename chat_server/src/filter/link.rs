@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use crate::error::AppError;
+use crate::filter::{MessageFilter, OutgoingMessage};
+use crate::models::MessageType;
+
+/// Rejects text messages containing links in the chats where the owner blocked them.
+pub(crate) struct LinkFilter {
+    pool: PgPool,
+}
+
+impl LinkFilter {
+    pub(crate) fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+        }
+    }
+}
+
+#[async_trait]
+impl MessageFilter for LinkFilter {
+    async fn check(&self, message: &mut OutgoingMessage) -> Result<(), AppError> {
+        if message.r#type != MessageType::Text || !contains_link(&message.content) {
+            return Ok(());
+        }
+
+        let block_links: Option<(bool,)> = sqlx::query_as(
+            r#"
+            SELECT block_links FROM chats WHERE id = $1
+            "#,
+        )
+            .bind(message.chat_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match block_links {
+            Some((true,)) => Err(AppError::MessageRejected("Links are not allowed in this chat".to_string())),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn contains_link(content: &str) -> bool {
+    find_link(content).is_some()
+}
+
+/// The first link in the content, `www.` links get an https scheme.
+pub(crate) fn find_link(content: &str) -> Option<String> {
+    content
+        .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '<' | '>' | '"'))
+        .map(|word| word.trim_end_matches(['.', ',', ';', ':', '!', '?']))
+        .find_map(|word| {
+            let lower = word.to_lowercase();
+            ["http://", "https://", "www."]
+                .iter()
+                .find(|prefix| lower.starts_with(*prefix) && lower.len() > prefix.len())
+                .map(|prefix| match *prefix {
+                    "www." => format!("https://{}", word),
+                    _ => word.to_string(),
+                })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_link_should_work() {
+        assert!(contains_link("see https://example.com"));
+        assert!(contains_link("see (WWW.example.com)"));
+        assert!(!contains_link("see example dot com"));
+        assert!(!contains_link("https:// alone"));
+    }
+
+    #[test]
+    fn find_link_should_work() {
+        assert_eq!(find_link("see https://example.com/a?b=1."), Some("https://example.com/a?b=1".to_string()));
+        assert_eq!(find_link("see (www.example.com) and http://b.com"), Some("https://www.example.com".to_string()));
+        assert_eq!(find_link("no link here"), None);
+    }
+}
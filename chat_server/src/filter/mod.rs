@@ -0,0 +1,64 @@
+mod keyword;
+mod length;
+mod link;
+
+pub(crate) use keyword::*;
+pub(crate) use length::*;
+pub(crate) use link::*;
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+use crate::config::MessageFilterConfig;
+use crate::error::AppError;
+use crate::models::{MessageType, UserId};
+
+/// A message about to be saved.
+#[derive(Debug, Clone)]
+pub(crate) struct OutgoingMessage {
+    pub(crate) chat_id: i64,
+    pub(crate) user_id: UserId,
+    pub(crate) r#type: MessageType,
+    pub(crate) content: String,
+    pub(crate) thread_root_id: Option<i64>,
+}
+
+/// A check run on every outgoing message before it is saved.
+/// Return an error to reject the message, or rewrite `message.content` to mask it.
+#[async_trait]
+pub(crate) trait MessageFilter: Send + Sync {
+    async fn check(&self, message: &mut OutgoingMessage) -> Result<(), AppError>;
+}
+
+/// Filters run in the order they are added, the first error stops the chain.
+#[derive(Default)]
+pub(crate) struct MessageFilterChain {
+    filters: Vec<Box<dyn MessageFilter>>,
+}
+
+impl MessageFilterChain {
+    /// The built-in filters enabled by the config.
+    pub(crate) fn from_config(config: &MessageFilterConfig, pool: PgPool) -> Self {
+        let mut chain = Self::default()
+            .with(LengthFilter::new(config.max_length))
+            .with(LinkFilter::new(pool));
+
+        if !config.blocked_keywords.is_empty() {
+            chain = chain.with(KeywordFilter::new(config.blocked_keywords.clone(), config.keyword_action));
+        }
+
+        chain
+    }
+
+    pub(crate) fn with(mut self, filter: impl MessageFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub(crate) async fn apply(&self, message: &mut OutgoingMessage) -> Result<(), AppError> {
+        for filter in &self.filters {
+            filter.check(message).await?;
+        }
+
+        Ok(())
+    }
+}
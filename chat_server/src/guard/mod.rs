@@ -0,0 +1,31 @@
+use async_graphql::{Context, ErrorExtensions, Guard};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::models::{UserId, UserRole};
+
+/// Allows the field only to users whose role is at least `role`.
+pub(crate) struct RoleGuard {
+    role: UserRole,
+}
+
+impl RoleGuard {
+    pub(crate) fn new(role: UserRole) -> Self {
+        Self {
+            role,
+        }
+    }
+}
+
+impl Guard for RoleGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError.extend())?;
+
+        let role = state.user_repo.get_role(*user_id).await.map_err(|e| e.extend())?;
+
+        match role {
+            Some(role) if role >= self.role => Ok(()),
+            _ => Err(AppError::Forbidden.extend()),
+        }
+    }
+}
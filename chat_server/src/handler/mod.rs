@@ -1,30 +1,33 @@
 use crate::app_state::AppState;
+use crate::loader::{ChatMembersLoader, ContactCardLoader, DraftLoader, ForwardOriginLoader, KnownUserLoader, LatestMessageLoader, LinkPreviewLoader, LocationLoader, MentionLoader, PollLoader, StickerLoader, SystemEventLoader, ThreadLoader, UnreadCountLoader, UnreadMentionCountLoader, UserLoader};
 use crate::error::AppError;
 use crate::middlewares::RequestIdToResponseLayer;
-use crate::models::{Message, User, UserId};
+use crate::models::{Message, SessionId, User, UserId};
 use crate::query::{QueryRoot};
-use async_graphql::futures_util::Stream;
+use async_graphql::dataloader::DataLoader;
+use async_graphql::futures_util::{Stream, StreamExt};
 use async_graphql::http::{
     playground_source, GraphQLPlaygroundConfig, GraphiQLSource, ALL_WEBSOCKET_PROTOCOLS,
 };
 use async_graphql::{
-    Context, Enum, Object, OutputType, Response, Schema, SimpleObject, Subscription, Union,
+    Context, Enum, ErrorExtensions, Object, OutputType, Pos, Response, Schema, SimpleObject, Subscription, Union,
 };
 use async_graphql_axum::{
     GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLSubscription, GraphQLWebSocket,
 };
-use axum::extract::{State, WebSocketUpgrade};
-use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum::extract::{Path, State, WebSocketUpgrade};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
 use axum::response::{Html, IntoResponse};
 use axum::routing::get;
 use axum::{Extension, response, Router};
 use serde::{Deserialize, Serialize};
 use sqlx::__rt::yield_now;
 use std::collections::HashSet;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 use axum::middleware::AddExtension;
 use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
 use tower_http::request_id::{MakeRequestId, PropagateRequestIdLayer, RequestId};
 use tower_http::trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer};
 use tower_http::{request_id, LatencyUnit};
@@ -35,7 +38,7 @@ use tracing_subscriber::registry::Data;
 use tracing_subscriber::{fmt::Layer, layer::SubscriberExt, util::SubscriberInitExt, Layer as _};
 use uuid::Uuid;
 use crate::mutation::MutationRoot;
-use crate::notification::Notification;
+use crate::notification::{AppEvent, Notification};
 use crate::subscription::SubscriptionRoot;
 
 #[derive(Enum, Eq, PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
@@ -57,11 +60,28 @@ pub(crate) async fn init_graphql_router(app_state: AppState) -> Router {
         SubscriptionRoot,
     )
         .data(app_state.clone())
+        .data(DataLoader::new(UserLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(KnownUserLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(ChatMembersLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(LatestMessageLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(UnreadCountLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(DraftLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(ForwardOriginLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(MentionLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(UnreadMentionCountLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(ThreadLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(PollLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(LinkPreviewLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(LocationLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(ContactCardLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(SystemEventLoader::new(app_state.clone()), tokio::spawn))
+        .data(DataLoader::new(StickerLoader::new(app_state.clone()), tokio::spawn))
         .finish();
 
     let router = Router::new()
         .route("/", get(graphiql).post(graphql_handler))
         .route("/ws", get(graphql_ws_handler))
+        .route("/exports/:id", get(download_export_handler))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(DefaultMakeSpan::new().include_headers(true))
@@ -101,46 +121,69 @@ async fn graphql_ws_handler(
 ) -> response::Response {
     websocket
         .protocols(ALL_WEBSOCKET_PROTOCOLS)
-        .on_upgrade(move |stream| {
-            GraphQLWebSocket::new(stream, schema.clone(), protocol)
+        .on_upgrade(move |socket| {
+            let (sink, stream) = socket.split();
+            let session_user = Arc::new(OnceLock::new());
+
+            // stop reading from the client when the user gets locked, which ends the session
+            let restricted = Box::pin(wait_for_restriction(state.clone(), session_user.clone()));
+            let stream = stream.take_until(restricted);
+
+            GraphQLWebSocket::new_with_pair(sink, stream, schema.clone(), protocol)
                 .on_connection_init(|x| async move {
-                    handle_connect_init(state.clone(), x).await
+                    handle_connect_init(state.clone(), session_user, x).await
                 })
                 .serve()
         })
 }
 
-pub async fn get_user_id_from_bearer_token(state: AppState, str: Option<&str>) -> Option<UserId> {
+/// Returns the user id of a valid token, or an error if the user is suspended or banned.
+pub async fn get_user_id_from_bearer_token(state: AppState, str: Option<&str>) -> Result<Option<UserId>, AppError> {
     if let Some(token) = str {
         if token.starts_with("Bearer ") {
             let token = token.trim_start_matches("Bearer ");
             let user_id = state.dk.verify(token);
 
             match user_id {
-                Ok(user_id) => Some(user_id),
-                Err(_) => None,
+                Ok(user_id) => {
+                    state.user_repo.check_restriction(user_id).await?;
+                    Ok(Some(user_id))
+                },
+                Err(_) => Ok(None),
             }
         } else {
-            None
+            Ok(None)
         }
     } else {
-        None
+        Ok(None)
     }
 }
 
 pub async fn handle_connect_init(
     state: AppState,
+    session_user: Arc<OnceLock<UserId>>,
     value: serde_json::Value,
 ) -> async_graphql::Result<async_graphql::Data> {
     let bearer_token_str = value
         .get("Authorization")
         .map(|v| v.as_str().unwrap_or_default());
 
-    let user_id = get_user_id_from_bearer_token(state.clone(), bearer_token_str).await;
+    let user_id = get_user_id_from_bearer_token(state.clone(), bearer_token_str)
+        .await
+        .map_err(|e| e.extend())?;
+
+    // every websocket is a session, even if the client does not name it
+    let session_id = value
+        .get("sessionId")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::now_v7().to_string());
 
     let mut data = async_graphql::Data::default();
+    data.insert(SessionId(session_id));
 
     if let Some(user_id) = user_id {
+        let _ = session_user.set(user_id);
         data.insert(user_id);
         Ok(data)
     } else {
@@ -148,6 +191,25 @@ pub async fn handle_connect_init(
     }
 }
 
+/// Resolves when the user of the session is suspended or banned.
+async fn wait_for_restriction(state: AppState, session_user: Arc<OnceLock<UserId>>) {
+    let mut rv = state.sender.subscribe();
+
+    loop {
+        match rv.recv().await {
+            Ok(noti) => {
+                if let AppEvent::AccountRestricted(restricted) = noti.event {
+                    if session_user.get() == Some(&restricted.user_id) {
+                        return;
+                    }
+                }
+            },
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => std::future::pending::<()>().await,
+        }
+    }
+}
+
 async fn graphql_handler(
     State(schema): State<Schema<QueryRoot, MutationRoot, SubscriptionRoot>>,
     Extension(state): Extension<AppState>,
@@ -161,11 +223,25 @@ async fn graphql_handler(
         .map(|v| v.to_str().unwrap_or_default());
     debug!("Bearer token: {:?}", token);
 
-    let user_id = get_user_id_from_bearer_token(state.clone(), token).await;
+    let user_id = match get_user_id_from_bearer_token(state.clone(), token).await {
+        Ok(user_id) => user_id,
+        Err(e) => {
+            let error = e.extend().into_server_error(Pos::default());
+            return Response::from_errors(vec![error]).into();
+        }
+    };
 
     if let Some(user_id) = user_id {
         req = req.data::<UserId>(user_id);
     }
+
+    let session_id = headers
+        .get("ichat-session-id")
+        .and_then(|v| v.to_str().ok());
+
+    if let Some(session_id) = session_id {
+        req = req.data(SessionId(session_id.to_string()));
+    }
     // FIXME: 这里没有授权的情况下，没有立刻终止
     // 因为我也不知道怎么返回错误
     // 但是如果需要授权的请求需要用到 userId，则一定会报错的，所以并没有太大的影响
@@ -173,6 +249,35 @@ async fn graphql_handler(
     schema.execute(req).await.into()
 }
 
+async fn download_export_handler(
+    Extension(state): Extension<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let token = headers
+        .get("Authorization")
+        .map(|v| v.to_str().unwrap_or_default());
+
+    let user_id = get_user_id_from_bearer_token(state.clone(), token)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+
+    let content = state.account_repo
+        .get_export_content(id, user_id)
+        .await?
+        .ok_or(AppError::DataExportNotFound)?;
+
+    let disposition = format!("attachment; filename=\"ichat-export-{}.json\"", id);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        serde_json::to_vec_pretty(&content)?,
+    ))
+}
+
 #[derive(Debug, Clone)]
 struct RequestIdGenerator;
 
@@ -0,0 +1,52 @@
+use std::time::Duration;
+use tracing::{error, info};
+use crate::app_state::AppState;
+use crate::models::UserId;
+use crate::notification::{AccountRestricted, AppEvent, Notification};
+
+const ACCOUNT_REAPER_INTERVAL: Duration = Duration::from_secs(60);
+
+pub(crate) fn spawn_data_export(state: AppState, export_id: i64, user_id: UserId) {
+    tokio::spawn(async move {
+        let ttl_hours = state.config.account.export_ttl_hours;
+
+        match state.account_repo.build_export(export_id, user_id, ttl_hours).await {
+            Ok(_) => info!("Data export {} finished", export_id),
+            Err(e) => {
+                error!("Data export {} failed: {:?}", export_id, e);
+                if let Err(e) = state.account_repo.mark_export_failed(export_id).await {
+                    error!("Mark data export {} failed error: {:?}", export_id, e);
+                }
+            }
+        }
+    });
+}
+
+/// Anonymize the accounts whose grace period is over.
+pub(crate) async fn run_account_reaper(state: AppState) {
+    let mut interval = tokio::time::interval(ACCOUNT_REAPER_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let user_ids = match state.account_repo.get_due_deletions().await {
+            Ok(user_ids) => user_ids,
+            Err(e) => {
+                error!("Get due account deletions error: {:?}", e);
+                continue;
+            }
+        };
+
+        for user_id in user_ids {
+            match state.account_repo.anonymize_user(user_id).await {
+                // close the open sessions of the deleted account
+                Ok(_) => {
+                    let _ = state.sender.send(Notification {
+                        event: AppEvent::AccountRestricted(AccountRestricted { user_id }),
+                    });
+                }
+                Err(e) => error!("Anonymize account {} error: {:?}", user_id, e),
+            }
+        }
+    }
+}
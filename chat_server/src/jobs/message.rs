@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::error;
+use crate::app_state::AppState;
+use crate::notification::{AppEvent, MessagesExpired, Notification};
+
+const MESSAGE_REAPER_INTERVAL: Duration = Duration::from_secs(30);
+const MESSAGE_REAPER_BATCH: i64 = 1000;
+
+/// Delete the disappearing messages whose timer ran out, and tell the members of each chat.
+pub(crate) async fn run_message_reaper(state: AppState) {
+    let mut interval = tokio::time::interval(MESSAGE_REAPER_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        loop {
+            let deleted = match state.message_repo.delete_expired_messages(MESSAGE_REAPER_BATCH).await {
+                Ok(deleted) => deleted,
+                Err(e) => {
+                    error!("Delete expired messages error: {:?}", e);
+                    break;
+                }
+            };
+
+            let done = (deleted.len() as i64) < MESSAGE_REAPER_BATCH;
+
+            let mut chats: BTreeMap<i64, Vec<i64>> = BTreeMap::new();
+            for (chat_id, message_id) in deleted {
+                chats.entry(chat_id).or_default().push(message_id);
+            }
+
+            for (chat_id, message_ids) in chats {
+                let member_ids = match state.chat_repo.get_member_ids(chat_id).await {
+                    Ok(member_ids) => member_ids,
+                    Err(e) => {
+                        error!("Get members of chat {} error: {:?}", chat_id, e);
+                        continue;
+                    }
+                };
+
+                let _ = state.sender.send(Notification {
+                    event: AppEvent::MessagesExpired(MessagesExpired {
+                        chat_id,
+                        message_ids,
+                        member_ids,
+                    }),
+                });
+            }
+
+            if done {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,18 @@
+mod account;
+mod message;
+mod preview;
+mod schedule;
+
+pub(crate) use account::*;
+pub(crate) use message::*;
+pub(crate) use preview::*;
+pub(crate) use schedule::*;
+
+use crate::app_state::AppState;
+
+/// Background jobs running next to the server.
+pub(crate) fn setup_jobs(state: AppState) {
+    tokio::spawn(run_account_reaper(state.clone()));
+    tokio::spawn(run_message_scheduler(state.clone()));
+    tokio::spawn(run_message_reaper(state));
+}
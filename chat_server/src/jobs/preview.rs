@@ -0,0 +1,38 @@
+use tracing::error;
+use crate::app_state::AppState;
+use crate::filter::find_link;
+use crate::models::{Message, MessageType};
+use crate::notification::{AppEvent, MessageUpdated, Notification};
+
+/// Attach a preview of the first link in a text message, then tell the chat the message changed.
+pub(crate) fn spawn_link_preview(state: AppState, message: Message) {
+    if !state.config.link_preview.enabled || message.r#type != MessageType::Text {
+        return;
+    }
+
+    let Some(url) = find_link(&message.content) else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let preview = match state.link_previews.preview(&url).await {
+            Ok(Some(preview)) => preview,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Link preview of message {} error: {:?}", message.id, e);
+                return;
+            }
+        };
+
+        if let Err(e) = state.message_repo.save_link_preview(message.chat_id, message.id, &preview).await {
+            error!("Save link preview of message {} error: {:?}", message.id, e);
+            return;
+        }
+
+        let _ = state.sender.send(Notification {
+            event: AppEvent::MessageUpdated(MessageUpdated {
+                data: message,
+            }),
+        });
+    });
+}
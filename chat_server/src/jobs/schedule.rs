@@ -0,0 +1,59 @@
+use std::time::Duration;
+use tracing::error;
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::filter::OutgoingMessage;
+use crate::jobs::spawn_link_preview;
+use crate::models::{MessageType, ScheduledMessage};
+
+const MESSAGE_SCHEDULER_INTERVAL: Duration = Duration::from_secs(5);
+const MESSAGE_SCHEDULER_BATCH: i64 = 100;
+
+/// Send the scheduled messages which are due.
+pub(crate) async fn run_message_scheduler(state: AppState) {
+    let mut interval = tokio::time::interval(MESSAGE_SCHEDULER_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let messages = match state.schedule_repo.claim_due_messages(MESSAGE_SCHEDULER_BATCH).await {
+            Ok(messages) => messages,
+            Err(e) => {
+                error!("Claim due scheduled messages error: {:?}", e);
+                continue;
+            }
+        };
+
+        for scheduled in messages {
+            let id = scheduled.id;
+
+            if let Err(e) = send_scheduled_message(&state, scheduled).await {
+                if let Err(e) = state.schedule_repo.mark_failed(id, e.to_string()).await {
+                    error!("Update scheduled message {} error: {:?}", id, e);
+                }
+            }
+        }
+    }
+}
+
+/// Goes through the same checks as `sendMessage`, the sender may have been restricted or left the chat meanwhile.
+async fn send_scheduled_message(state: &AppState, scheduled: ScheduledMessage) -> Result<(), AppError> {
+    let id = scheduled.id;
+
+    state.user_repo.check_restriction(scheduled.user_id).await?;
+
+    let mut message = OutgoingMessage {
+        chat_id: scheduled.chat_id,
+        user_id: scheduled.user_id,
+        r#type: MessageType::Text,
+        content: scheduled.content,
+        thread_root_id: None,
+    };
+    state.message_filters.apply(&mut message).await?;
+
+    if let Some(message) = state.schedule_repo.send_claimed_message(id, message).await? {
+        spawn_link_preview(state.clone(), message);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use async_graphql::dataloader::Loader;
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::models::{ContactPayload, Draft, ForwardOrigin, LinkPreview, LocationPayload, Message, MessageMentions, MessageThread, Poll, Sticker, SystemPayload, User, UserId};
+
+/// Loads the members of chats, keyed by chat id.
+pub(crate) struct ChatMembersLoader {
+    state: AppState,
+}
+
+impl ChatMembersLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<i64> for ChatMembersLoader {
+    type Value = Vec<User>;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> {
+        let rows = self.state.chat_repo.get_members_by_chat_ids(keys).await?;
+
+        let mut members: HashMap<i64, Vec<User>> = HashMap::new();
+        for (chat_id, user) in rows {
+            members.entry(chat_id).or_default().push(user);
+        }
+
+        Ok(members)
+    }
+}
+
+/// Loads the latest message of chats, keyed by chat id.
+pub(crate) struct LatestMessageLoader {
+    state: AppState,
+}
+
+impl LatestMessageLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<i64> for LatestMessageLoader {
+    type Value = Message;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> {
+        let messages = self.state.chat_repo.get_latest_messages(keys).await?;
+
+        Ok(messages.into_iter().map(|m| (m.chat_id, m)).collect())
+    }
+}
+
+/// Loads unread counts, keyed by (chat id, user id).
+pub(crate) struct UnreadCountLoader {
+    state: AppState,
+}
+
+impl UnreadCountLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, UserId)> for UnreadCountLoader {
+    type Value = i32;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, UserId)]) -> Result<HashMap<(i64, UserId), Self::Value>, Self::Error> {
+        let counts = self.state.chat_repo.get_unread_counts(keys).await?;
+
+        Ok(counts.into_iter().map(|(chat_id, user_id, count)| ((chat_id, user_id), count)).collect())
+    }
+}
+
+/// Loads drafts, keyed by (chat id, user id).
+pub(crate) struct DraftLoader {
+    state: AppState,
+}
+
+impl DraftLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, UserId)> for DraftLoader {
+    type Value = Draft;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, UserId)]) -> Result<HashMap<(i64, UserId), Self::Value>, Self::Error> {
+        let drafts = self.state.chat_repo.get_drafts(keys).await?;
+
+        Ok(drafts.into_iter().map(|d| ((d.chat_id, d.user_id), d)).collect())
+    }
+}
+
+/// Loads the origin of forwarded messages, keyed by (chat id, message id).
+pub(crate) struct ForwardOriginLoader {
+    state: AppState,
+}
+
+impl ForwardOriginLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, i64)> for ForwardOriginLoader {
+    type Value = ForwardOrigin;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, i64)]) -> Result<HashMap<(i64, i64), Self::Value>, Self::Error> {
+        let origins = self.state.message_repo.get_forward_origins(keys).await?;
+
+        Ok(origins.into_iter().map(|o| ((o.chat_id, o.message_id), o)).collect())
+    }
+}
+
+/// Loads the mentions of messages, keyed by (chat id, message id).
+pub(crate) struct MentionLoader {
+    state: AppState,
+}
+
+impl MentionLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, i64)> for MentionLoader {
+    type Value = MessageMentions;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, i64)]) -> Result<HashMap<(i64, i64), Self::Value>, Self::Error> {
+        let mentions = self.state.message_repo.get_mentions(keys).await?;
+
+        Ok(mentions.into_iter().map(|m| ((m.chat_id, m.message_id), m)).collect())
+    }
+}
+
+/// Loads unread mention counts, keyed by (chat id, user id).
+pub(crate) struct UnreadMentionCountLoader {
+    state: AppState,
+}
+
+impl UnreadMentionCountLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, UserId)> for UnreadMentionCountLoader {
+    type Value = i32;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, UserId)]) -> Result<HashMap<(i64, UserId), Self::Value>, Self::Error> {
+        let counts = self.state.chat_repo.get_unread_mention_counts(keys).await?;
+
+        Ok(counts.into_iter().map(|(chat_id, user_id, count)| ((chat_id, user_id), count)).collect())
+    }
+}
+
+/// Loads the reply count and last reply time of threads, keyed by (chat id, root message id).
+pub(crate) struct ThreadLoader {
+    state: AppState,
+}
+
+impl ThreadLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, i64)> for ThreadLoader {
+    type Value = MessageThread;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, i64)]) -> Result<HashMap<(i64, i64), Self::Value>, Self::Error> {
+        let threads = self.state.message_repo.get_threads(keys).await?;
+
+        Ok(threads.into_iter().map(|t| ((t.chat_id, t.root_id), t)).collect())
+    }
+}
+
+/// Loads polls with their tallies, keyed by (chat id, message id).
+pub(crate) struct PollLoader {
+    state: AppState,
+}
+
+impl PollLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, i64)> for PollLoader {
+    type Value = Poll;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, i64)]) -> Result<HashMap<(i64, i64), Self::Value>, Self::Error> {
+        let polls = self.state.poll_repo.get_polls(keys).await?;
+
+        Ok(polls.into_iter().map(|p| ((p.chat_id, p.message_id), p)).collect())
+    }
+}
+
+/// Loads link previews of messages, keyed by (chat id, message id).
+pub(crate) struct LinkPreviewLoader {
+    state: AppState,
+}
+
+impl LinkPreviewLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, i64)> for LinkPreviewLoader {
+    type Value = LinkPreview;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, i64)]) -> Result<HashMap<(i64, i64), Self::Value>, Self::Error> {
+        let previews = self.state.message_repo.get_link_previews(keys).await?;
+
+        Ok(previews.into_iter().map(|p| ((p.chat_id, p.message_id), p.preview)).collect())
+    }
+}
+
+/// Loads the payloads of location messages, keyed by (chat id, message id).
+pub(crate) struct LocationLoader {
+    state: AppState,
+}
+
+impl LocationLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, i64)> for LocationLoader {
+    type Value = LocationPayload;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, i64)]) -> Result<HashMap<(i64, i64), Self::Value>, Self::Error> {
+        let locations = self.state.message_repo.get_locations(keys).await?;
+
+        Ok(locations.into_iter().map(|l| ((l.chat_id, l.message_id), l)).collect())
+    }
+}
+
+/// Loads the payloads of contact card messages, keyed by (chat id, message id).
+pub(crate) struct ContactCardLoader {
+    state: AppState,
+}
+
+impl ContactCardLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, i64)> for ContactCardLoader {
+    type Value = ContactPayload;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, i64)]) -> Result<HashMap<(i64, i64), Self::Value>, Self::Error> {
+        let contacts = self.state.message_repo.get_contacts(keys).await?;
+
+        Ok(contacts.into_iter().map(|c| ((c.chat_id, c.message_id), c)).collect())
+    }
+}
+
+/// Loads the events of system messages, keyed by (chat id, message id).
+pub(crate) struct SystemEventLoader {
+    state: AppState,
+}
+
+impl SystemEventLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, i64)> for SystemEventLoader {
+    type Value = SystemPayload;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, i64)]) -> Result<HashMap<(i64, i64), Self::Value>, Self::Error> {
+        let events = self.state.message_repo.get_system_events(keys).await?;
+
+        Ok(events.into_iter().map(|e| ((e.chat_id, e.message_id), e)).collect())
+    }
+}
+
+/// Loads the stickers of sticker messages, keyed by (chat id, message id).
+pub(crate) struct StickerLoader {
+    state: AppState,
+}
+
+impl StickerLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(i64, i64)> for StickerLoader {
+    type Value = Sticker;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(i64, i64)]) -> Result<HashMap<(i64, i64), Self::Value>, Self::Error> {
+        let stickers = self.state.sticker_repo.get_message_stickers(keys).await?;
+
+        Ok(stickers.into_iter().map(|s| ((s.chat_id, s.message_id), s.sticker)).collect())
+    }
+}
@@ -0,0 +1,5 @@
+mod user;
+mod chat;
+
+pub(crate) use user::*;
+pub(crate) use chat::*;
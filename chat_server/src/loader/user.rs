@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use async_graphql::dataloader::Loader;
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::models::{User, UserId};
+
+pub(crate) struct UserLoader {
+    state: AppState,
+}
+
+impl UserLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<UserId> for UserLoader {
+    type Value = User;
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[UserId]) -> Result<HashMap<UserId, Self::Value>, Self::Error> {
+        let users = self.state.user_repo.find_by_ids(keys).await?;
+
+        Ok(users.into_iter().map(|u| (u.id, u)).collect())
+    }
+}
+
+/// Loads whether a user knows another one, keyed by (viewer id, user id).
+/// Users know each other when they are contacts or share at least one chat.
+pub(crate) struct KnownUserLoader {
+    state: AppState,
+}
+
+impl KnownUserLoader {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<(UserId, UserId)> for KnownUserLoader {
+    type Value = ();
+    type Error = Arc<AppError>;
+
+    async fn load(&self, keys: &[(UserId, UserId)]) -> Result<HashMap<(UserId, UserId), Self::Value>, Self::Error> {
+        let known = self.state.user_repo.get_known_users(keys).await?;
+
+        Ok(known.into_iter().map(|k| (k, ())).collect())
+    }
+}
@@ -10,6 +10,11 @@ mod query;
 mod mutation;
 mod subscription;
 mod notification;
+mod loader;
+mod jobs;
+mod guard;
+mod filter;
+mod preview;
 
 use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
@@ -25,6 +30,7 @@ use tracing::{error, info};
 use crate::app_state::AppState;
 use crate::config::AppConfig;
 use crate::handler::{init_graphql_router};
+use crate::jobs::setup_jobs;
 use crate::notification::setup_pg_listener;
 
 #[tokio::main]
@@ -38,6 +44,7 @@ async fn main() -> Result<()> {
     info!("Listening on {address}");
 
     tokio::spawn(setup_pg_listener(app_state.clone()));
+    setup_jobs(app_state.clone());
 
     axum::serve(listener, app.into_make_service()).await?;
 
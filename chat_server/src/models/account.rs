@@ -0,0 +1,44 @@
+use async_graphql::{ComplexObject, Enum, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::models::UserId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, sqlx::Type, Enum, Copy, Eq)]
+#[sqlx(type_name = "data_export_status", rename_all = "snake_case")]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub enum DataExportStatus {
+    Pending,
+    Ready,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[graphql(complex)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct DataExport {
+    pub(crate) id: i64,
+    pub(crate) user_id: UserId,
+    pub(crate) status: DataExportStatus,
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) finished_at: Option<DateTime<Utc>>,
+    pub(crate) expires_at: Option<DateTime<Utc>>,
+}
+
+#[ComplexObject]
+impl DataExport {
+    /// Path to download the archive with the bearer token, only when the export is ready.
+    async fn download_url(&self) -> Option<String> {
+        match self.status {
+            DataExportStatus::Ready => Some(format!("/exports/{}", self.id)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct AccountDeletion {
+    /// The account is anonymized after this time, unless the deletion is cancelled.
+    pub(crate) scheduled_at: DateTime<Utc>,
+}
@@ -0,0 +1,64 @@
+use async_graphql::{Enum, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::models::{ChatType, UserId};
+
+/// System-level role, ordered by privilege.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, sqlx::Type, Enum, Copy, Eq, Ord)]
+#[sqlx(type_name = "user_role", rename_all = "snake_case")]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub enum UserRole {
+    User,
+    Moderator,
+    Admin,
+}
+
+/// A user as operators see it, without any privacy setting applied.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct AdminUser {
+    pub(crate) id: UserId,
+    pub(crate) fullname: String,
+    pub(crate) email: String,
+    pub(crate) avatar: Option<String>,
+    pub(crate) role: UserRole,
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) suspended_until: Option<DateTime<Utc>>,
+    /// Reason of the suspension or the ban.
+    pub(crate) suspension_reason: Option<String>,
+    pub(crate) banned: bool,
+    pub(crate) deletion_scheduled_at: Option<DateTime<Utc>>,
+    pub(crate) deleted_at: Option<DateTime<Utc>>,
+}
+
+/// A chat as operators see it, without the fields that only make sense for its members.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct AdminChat {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    pub(crate) owner_id: UserId,
+    pub(crate) r#type: ChatType,
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) last_activity_at: DateTime<Utc>,
+    pub(crate) block_links: bool,
+    pub(crate) message_ttl_seconds: Option<i32>,
+    pub(crate) member_ids: Vec<UserId>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct SystemStats {
+    pub(crate) user_count: i64,
+    /// Users registered in the last 24 hours.
+    pub(crate) new_user_count: i64,
+    /// Users suspended or banned.
+    pub(crate) suspended_user_count: i64,
+    pub(crate) chat_count: i64,
+    pub(crate) group_chat_count: i64,
+    pub(crate) message_count: i64,
+    /// Messages sent in the last 24 hours.
+    pub(crate) new_message_count: i64,
+    pub(crate) open_report_count: i64,
+}
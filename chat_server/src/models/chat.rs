@@ -1,10 +1,12 @@
 use async_graphql::{ComplexObject, Context, InputObject, SimpleObject};
+use async_graphql::dataloader::DataLoader;
 use chrono::{DateTime, Utc};
 use jwt_simple::prelude::{Deserialize, Serialize};
 use sqlx::FromRow;
 use crate::app_state::AppState;
 use crate::error::AppError;
-use crate::models::{ChatType, Message, User, UserId};
+use crate::loader::{ChatMembersLoader, DraftLoader, LatestMessageLoader, UnreadCountLoader, UnreadMentionCountLoader, UserLoader};
+use crate::models::{ChatType, Draft, Message, PinnedMessage, User, UserId};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject, InputObject)]
 #[graphql(complex)]
@@ -15,6 +17,11 @@ pub struct Chat {
     pub(crate) owner_id: UserId,
     pub(crate) r#type: ChatType,
     pub(crate) created_at: DateTime<Utc>,
+    pub(crate) last_activity_at: DateTime<Utc>,
+    /// Messages containing links are rejected.
+    pub(crate) block_links: bool,
+    /// Seconds after which new messages disappear, `None` keeps them.
+    pub(crate) message_ttl_seconds: Option<i32>,
 }
 
 #[ComplexObject]
@@ -37,8 +44,7 @@ impl Chat {
     }
 
     async fn original_9_users(&self, ctx : &Context<'_>) -> anyhow::Result<Vec<User>, AppError> {
-        let state = ctx.data_unchecked::<AppState>();
-        let users = state.chat_repo.get_members(self.id).await?;
+        let users = self.members(ctx).await?;
         let users = users.into_iter().take(9).collect();
 
         Ok(users)
@@ -53,8 +59,8 @@ impl Chat {
     }
 
     async fn owner(&self, ctx : &Context<'_>) -> anyhow::Result<User, AppError> {
-        let state = ctx.data_unchecked::<AppState>();
-        let user = state.user_repo.find_by_id(self.owner_id).await?;
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        let user = loader.load_one(self.owner_id).await?;
 
         match user {
             Some(user) => Ok(user),
@@ -63,15 +69,15 @@ impl Chat {
     }
 
     async fn latest_message(&self, ctx : &Context<'_>) -> anyhow::Result<Option<Message>, AppError> {
-        let state = ctx.data_unchecked::<AppState>();
-        let message = state.chat_repo.get_latest_message(self.id).await?;
+        let loader = ctx.data_unchecked::<DataLoader<LatestMessageLoader>>();
+        let message = loader.load_one(self.id).await?;
         Ok(message)
     }
 
     async fn members(&self, ctx : &Context<'_>) -> anyhow::Result<Vec<User>, AppError> {
-        let state = ctx.data_unchecked::<AppState>();
-        let users = state.chat_repo.get_members(self.id).await?;
-        Ok(users)
+        let loader = ctx.data_unchecked::<DataLoader<ChatMembersLoader>>();
+        let users = loader.load_one(self.id).await?;
+        Ok(users.unwrap_or_default())
     }
 
     async fn unread_count(&self, ctx: &Context<'_>) -> anyhow::Result<i32, AppError> {
@@ -79,8 +85,86 @@ impl Chat {
             .data::<UserId>()
             .map_err(|_| AppError::GetGraphqlUserIdError)?;
 
+        let loader = ctx.data_unchecked::<DataLoader<UnreadCountLoader>>();
+        let count = loader.load_one((self.id, *user_id)).await?;
+        Ok(count.unwrap_or_default())
+    }
+
+    /// Unread messages which mention me.
+    async fn unread_mention_count(&self, ctx: &Context<'_>) -> anyhow::Result<i32, AppError> {
+        let user_id = ctx
+            .data::<UserId>()
+            .map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let loader = ctx.data_unchecked::<DataLoader<UnreadMentionCountLoader>>();
+        let count = loader.load_one((self.id, *user_id)).await?;
+        Ok(count.unwrap_or_default())
+    }
+
+    /// My unsent draft in the chat.
+    async fn draft(&self, ctx: &Context<'_>) -> anyhow::Result<Option<Draft>, AppError> {
+        let user_id = ctx
+            .data::<UserId>()
+            .map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let loader = ctx.data_unchecked::<DataLoader<DraftLoader>>();
+        let draft = loader.load_one((self.id, *user_id)).await?;
+        Ok(draft)
+    }
+
+    async fn settings(&self, ctx: &Context<'_>) -> anyhow::Result<ChatMemberSettings, AppError> {
+        let user_id = ctx
+            .data::<UserId>()
+            .map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let state = ctx.data_unchecked::<AppState>();
+        let settings = state.chat_repo.get_member_settings(self.id, *user_id).await?;
+        Ok(settings)
+    }
+
+    /// Latest pinned first.
+    async fn pinned_messages(&self, ctx: &Context<'_>) -> anyhow::Result<Vec<PinnedMessage>, AppError> {
         let state = ctx.data_unchecked::<AppState>();
-        let count = state.chat_repo.get_unread_count(self.id, *user_id).await?;
-        Ok(count)
+        let pinned = state.message_repo.get_pinned_messages(self.id).await?;
+        Ok(pinned)
     }
+
+    async fn nicknames(&self, ctx: &Context<'_>) -> anyhow::Result<Vec<ChatNickname>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let nicknames = state.chat_repo.get_nicknames(self.id).await?;
+        Ok(nicknames)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[graphql(complex)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct ChatMemberSettings {
+    pub(crate) chat_id: i64,
+    pub(crate) user_id: UserId,
+    pub(crate) muted_until: Option<DateTime<Utc>>,
+    pub(crate) pinned_order: Option<i32>,
+    pub(crate) archived: bool,
+    pub(crate) nickname: Option<String>,
+}
+
+#[ComplexObject]
+impl ChatMemberSettings {
+    async fn is_muted(&self) -> bool {
+        match self.muted_until {
+            Some(muted_until) => muted_until > Utc::now(),
+            None => false,
+        }
+    }
+
+    async fn is_pinned(&self) -> bool {
+        self.pinned_order.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct ChatNickname {
+    pub(crate) user_id: UserId,
+    pub(crate) nickname: String,
 }
@@ -0,0 +1,47 @@
+use async_graphql::{ComplexObject, Context, Enum, SimpleObject};
+use async_graphql::dataloader::DataLoader;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::error::AppError;
+use crate::loader::UserLoader;
+use crate::models::{User, UserId};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, sqlx::Type, Enum, Copy, Eq)]
+#[sqlx(type_name = "friend_request_status", rename_all = "snake_case")]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub enum FriendRequestStatus {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[graphql(complex)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct FriendRequest {
+    pub(crate) id: i64,
+    pub(crate) from_user_id: UserId,
+    pub(crate) to_user_id: UserId,
+    pub(crate) message: Option<String>,
+    pub(crate) status: FriendRequestStatus,
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl FriendRequest {
+    async fn from_user(&self, ctx: &Context<'_>) -> Result<User, AppError> {
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        let user = loader.load_one(self.from_user_id).await?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+
+    async fn to_user(&self, ctx: &Context<'_>) -> Result<User, AppError> {
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        let user = loader.load_one(self.to_user_id).await?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+}
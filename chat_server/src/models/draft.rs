@@ -0,0 +1,20 @@
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::models::UserId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct Draft {
+    pub(crate) chat_id: i64,
+    #[graphql(skip)]
+    pub(crate) user_id: UserId,
+    pub(crate) content: String,
+    pub(crate) updated_at: DateTime<Utc>,
+}
+
+/// Identifies one device of a user, so events can skip the session which caused them.
+/// Sent in the `ichat-session-id` header, or as `sessionId` when the websocket connects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SessionId(pub(crate) String);
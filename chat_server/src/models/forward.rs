@@ -0,0 +1,85 @@
+use async_graphql::{ComplexObject, Context, SimpleObject};
+use async_graphql::dataloader::DataLoader;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::loader::UserLoader;
+use crate::models::{Chat, Message, User, UserId};
+
+/// Where a forwarded message comes from.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[graphql(complex)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct ForwardOrigin {
+    /// The forwarded copy.
+    #[graphql(skip)]
+    pub(crate) chat_id: i64,
+    #[graphql(skip)]
+    pub(crate) message_id: i64,
+    #[graphql(skip)]
+    pub(crate) origin_user_id: Option<UserId>,
+    /// False if the sender does not allow to link their account.
+    #[graphql(skip)]
+    pub(crate) attributed: bool,
+    /// Name of the sender when the message was forwarded.
+    #[graphql(name = "senderName")]
+    pub(crate) origin_sender_name: String,
+    #[graphql(skip)]
+    pub(crate) origin_chat_id: Option<i64>,
+    #[graphql(skip)]
+    pub(crate) origin_message_id: Option<i64>,
+    #[graphql(name = "createdAt")]
+    pub(crate) origin_created_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl ForwardOrigin {
+    async fn sender(&self, ctx: &Context<'_>) -> Result<Option<User>, AppError> {
+        let Some(origin_user_id) = self.origin_user_id.filter(|_| self.attributed) else {
+            return Ok(None);
+        };
+
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        let user = loader.load_one(origin_user_id).await?;
+
+        Ok(user)
+    }
+
+    /// The original chat, only if I am a member of it.
+    async fn chat(&self, ctx: &Context<'_>) -> Result<Option<Chat>, AppError> {
+        let user_id = ctx
+            .data::<UserId>()
+            .map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let Some(origin_chat_id) = self.origin_chat_id else {
+            return Ok(None);
+        };
+
+        let state = ctx.data_unchecked::<AppState>();
+        let chat = state.chat_repo.get_chat_by_id(origin_chat_id, *user_id).await.ok();
+
+        Ok(chat)
+    }
+
+    /// The original message id, only if I am a member of the original chat.
+    async fn message_id(&self, ctx: &Context<'_>) -> Result<Option<i64>, AppError> {
+        let chat = self.chat(ctx).await?;
+
+        Ok(chat.and(self.origin_message_id))
+    }
+}
+
+/// A message about to be forwarded, with the origin its copies will record.
+#[derive(Debug, Clone, FromRow)]
+pub(crate) struct ForwardSource {
+    #[sqlx(flatten)]
+    pub(crate) message: Message,
+    pub(crate) origin_user_id: Option<UserId>,
+    pub(crate) attributed: bool,
+    pub(crate) origin_sender_name: String,
+    pub(crate) origin_chat_id: Option<i64>,
+    pub(crate) origin_message_id: Option<i64>,
+    pub(crate) origin_created_at: DateTime<Utc>,
+}
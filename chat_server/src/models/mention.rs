@@ -0,0 +1,19 @@
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::models::UserId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct MessageMentions {
+    pub(crate) chat_id: i64,
+    pub(crate) message_id: i64,
+    pub(crate) user_ids: Vec<UserId>,
+    pub(crate) mention_all: bool,
+}
+
+impl MessageMentions {
+    pub(crate) fn mentions(&self, user_id: UserId) -> bool {
+        self.mention_all || self.user_ids.contains(&user_id)
+    }
+}
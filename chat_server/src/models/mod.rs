@@ -1,6 +1,23 @@
 mod chat;
+mod contact;
+mod pagination;
+mod search;
+mod report;
+mod account;
+mod admin;
+mod draft;
+mod pin;
+mod forward;
+mod mention;
+mod thread;
+mod schedule;
+mod poll;
+mod preview;
+mod payload;
+mod sticker;
 
 use async_graphql::{ComplexObject, Context, Enum, SimpleObject};
+use async_graphql::dataloader::DataLoader;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
@@ -8,7 +25,24 @@ use anyhow::Result;
 
 use crate::app_state::AppState;
 use crate::error::AppError;
+use crate::loader::{ContactCardLoader, ForwardOriginLoader, KnownUserLoader, LinkPreviewLoader, LocationLoader, MentionLoader, PollLoader, StickerLoader, SystemEventLoader, ThreadLoader, UserLoader};
 pub(crate) use chat::*;
+pub(crate) use contact::*;
+pub(crate) use pagination::*;
+pub(crate) use search::*;
+pub(crate) use report::*;
+pub(crate) use account::*;
+pub(crate) use admin::*;
+pub(crate) use draft::*;
+pub(crate) use pin::*;
+pub(crate) use forward::*;
+pub(crate) use mention::*;
+pub(crate) use thread::*;
+pub(crate) use schedule::*;
+pub(crate) use poll::*;
+pub(crate) use preview::*;
+pub(crate) use payload::*;
+pub(crate) use sticker::*;
 
 pub type UserId = i64;
 
@@ -18,12 +52,20 @@ pub type UserId = i64;
 pub struct User {
     pub id: UserId,
     pub fullname: String,
+    #[graphql(skip)]
     pub email: String,
     #[sqlx(default)]
     #[serde(skip)]
     pub password_hash: Option<String>,
+    #[graphql(skip)]
     pub avatar: Option<String>,
     pub created_at: DateTime<Utc>,
+    #[graphql(skip)]
+    pub email_visibility: Visibility,
+    #[graphql(skip)]
+    pub avatar_visibility: Visibility,
+    pub bio: Option<String>,
+    pub status_text: Option<String>,
 }
 
 #[ComplexObject]
@@ -35,6 +77,68 @@ impl User {
 
         Ok(self.id == *user_id)
     }
+
+    /// Null if the user hides the email from the viewer.
+    async fn email(&self, ctx: &Context<'_>) -> Result<Option<String>, AppError> {
+        if self.is_visible_to_viewer(ctx, self.email_visibility).await? {
+            Ok(Some(self.email.clone()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Null if the user has no avatar or hides it from the viewer.
+    async fn avatar(&self, ctx: &Context<'_>) -> Result<Option<String>, AppError> {
+        if self.is_visible_to_viewer(ctx, self.avatar_visibility).await? {
+            Ok(self.avatar.clone())
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl User {
+    /// Whether a field with the given visibility can be shown to the current user.
+    async fn is_visible_to_viewer(&self, ctx: &Context<'_>, visibility: Visibility) -> Result<bool, AppError> {
+        let user_id = ctx
+            .data::<UserId>()
+            .map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        if self.id == *user_id {
+            return Ok(true);
+        }
+
+        match visibility {
+            Visibility::Everyone => Ok(true),
+            Visibility::Nobody => Ok(false),
+            Visibility::Contacts => {
+                let loader = ctx.data_unchecked::<DataLoader<KnownUserLoader>>();
+                let known = loader.load_one((*user_id, self.id)).await?;
+                Ok(known.is_some())
+            }
+        }
+    }
+}
+
+/// Who can see a profile field. Contacts are my contacts and the users sharing a chat with me.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, sqlx::Type, Enum, Copy, Eq)]
+#[sqlx(type_name = "visibility", rename_all = "snake_case")]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub enum Visibility {
+    Everyone,
+    Contacts,
+    Nobody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct PrivacySettings {
+    /// Whether others can find me by searching my email.
+    pub email_discoverable: bool,
+    pub email_visibility: Visibility,
+    pub avatar_visibility: Visibility,
+    /// Whether forwarded copies of my messages link to my account.
+    pub forward_attribution: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, sqlx::Type, Enum, Copy, Eq)]
@@ -54,6 +158,11 @@ pub enum MessageType {
     Video,
     Audio,
     File,
+    Poll,
+    Location,
+    Contact,
+    System,
+    Sticker,
 }
 
 
@@ -67,13 +176,17 @@ pub struct Message {
     pub r#type: MessageType,
     pub content: String,
     pub created_at: DateTime<Utc>,
+    /// Root message of the thread this message replies in.
+    pub thread_root_id: Option<i64>,
+    /// The message is deleted after this time, when the chat has disappearing messages on.
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 #[ComplexObject]
 impl Message {
     async fn user(&self, ctx: &Context<'_>) -> Result<User, AppError> {
-        let state = ctx.data_unchecked::<AppState>();
-        let user = state.user_repo.find_by_id(self.user_id).await?;
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        let user = loader.load_one(self.user_id).await?;
 
         match user {
             Some(user) => Ok(user),
@@ -81,6 +194,113 @@ impl Message {
         }
     }
 
+    /// Users mentioned with `@user`, not including `@all`.
+    async fn mentions(&self, ctx: &Context<'_>) -> Result<Vec<User>, AppError> {
+        let loader = ctx.data_unchecked::<DataLoader<MentionLoader>>();
+        let Some(mentions) = loader.load_one((self.chat_id, self.id)).await? else {
+            return Ok(vec![]);
+        };
+
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        let users = loader.load_many(mentions.user_ids.iter().cloned()).await?;
+
+        Ok(mentions.user_ids.iter().filter_map(|id| users.get(id).cloned()).collect())
+    }
+
+    async fn mentions_all(&self, ctx: &Context<'_>) -> Result<bool, AppError> {
+        let loader = ctx.data_unchecked::<DataLoader<MentionLoader>>();
+        let mentions = loader.load_one((self.chat_id, self.id)).await?;
+
+        Ok(mentions.is_some_and(|m| m.mention_all))
+    }
+
+    /// Whether I am mentioned, by `@user` or `@all`.
+    async fn mentions_me(&self, ctx: &Context<'_>) -> Result<bool, AppError> {
+        let user_id = ctx
+            .data::<UserId>()
+            .map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let loader = ctx.data_unchecked::<DataLoader<MentionLoader>>();
+        let mentions = loader.load_one((self.chat_id, self.id)).await?;
+
+        Ok(self.user_id != *user_id && mentions.is_some_and(|m| m.mentions(*user_id)))
+    }
+
+    /// Origin of the message if it was forwarded.
+    async fn forwarded_from(&self, ctx: &Context<'_>) -> Result<Option<ForwardOrigin>, AppError> {
+        let loader = ctx.data_unchecked::<DataLoader<ForwardOriginLoader>>();
+        let origin = loader.load_one((self.chat_id, self.id)).await?;
+
+        Ok(origin)
+    }
+
+    /// Preview of the first link, added shortly after a text message is sent.
+    async fn link_preview(&self, ctx: &Context<'_>) -> Result<Option<LinkPreview>, AppError> {
+        if self.r#type != MessageType::Text {
+            return Ok(None);
+        }
+
+        let loader = ctx.data_unchecked::<DataLoader<LinkPreviewLoader>>();
+        let preview = loader.load_one((self.chat_id, self.id)).await?;
+
+        Ok(preview)
+    }
+
+    /// The location, contact card, sticker or system event of messages of those types.
+    async fn payload(&self, ctx: &Context<'_>) -> Result<Option<MessagePayload>, AppError> {
+        let key = (self.chat_id, self.id);
+
+        let payload = match self.r#type {
+            MessageType::Location => {
+                let loader = ctx.data_unchecked::<DataLoader<LocationLoader>>();
+                loader.load_one(key).await?.map(MessagePayload::Location)
+            }
+            MessageType::Contact => {
+                let loader = ctx.data_unchecked::<DataLoader<ContactCardLoader>>();
+                loader.load_one(key).await?.map(MessagePayload::Contact)
+            }
+            MessageType::System => {
+                let loader = ctx.data_unchecked::<DataLoader<SystemEventLoader>>();
+                loader.load_one(key).await?.map(MessagePayload::System)
+            }
+            MessageType::Sticker => {
+                let loader = ctx.data_unchecked::<DataLoader<StickerLoader>>();
+                loader.load_one(key).await?.map(MessagePayload::Sticker)
+            }
+            _ => None,
+        };
+
+        Ok(payload)
+    }
+
+    /// The poll of a message of type poll.
+    async fn poll(&self, ctx: &Context<'_>) -> Result<Option<Poll>, AppError> {
+        if self.r#type != MessageType::Poll {
+            return Ok(None);
+        }
+
+        let loader = ctx.data_unchecked::<DataLoader<PollLoader>>();
+        let poll = loader.load_one((self.chat_id, self.id)).await?;
+
+        Ok(poll)
+    }
+
+    /// Number of replies in the thread started by this message.
+    async fn reply_count(&self, ctx: &Context<'_>) -> Result<i32, AppError> {
+        let loader = ctx.data_unchecked::<DataLoader<ThreadLoader>>();
+        let thread = loader.load_one((self.chat_id, self.id)).await?;
+
+        Ok(thread.map_or(0, |t| t.reply_count))
+    }
+
+    /// Time of the latest reply in the thread started by this message.
+    async fn last_reply_at(&self, ctx: &Context<'_>) -> Result<Option<DateTime<Utc>>, AppError> {
+        let loader = ctx.data_unchecked::<DataLoader<ThreadLoader>>();
+        let thread = loader.load_one((self.chat_id, self.id)).await?;
+
+        Ok(thread.map(|t| t.last_reply_at))
+    }
+
     async fn is_mine(&self, ctx: &Context<'_>) -> Result<bool, AppError> {
         let user_id = ctx
             .data::<UserId>()
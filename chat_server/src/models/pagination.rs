@@ -0,0 +1,78 @@
+use async_graphql::connection::CursorType;
+use chrono::{DateTime, Utc};
+use crate::config::MessageConfig;
+use crate::error::AppError;
+
+/// Clamp the `first` argument of a connection into `1..=max_page_size` of the config.
+pub(crate) fn page_size(first: Option<i32>, config: &MessageConfig) -> i64 {
+    first.unwrap_or(config.default_page_size).clamp(1, config.max_page_size) as i64
+}
+
+/// Position of a chat in the chat list, which is sorted by pinned order then last activity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ChatCursor {
+    pub(crate) pinned_order: i32,
+    pub(crate) last_activity_at: DateTime<Utc>,
+    pub(crate) id: i64,
+}
+
+impl CursorType for ChatCursor {
+    type Error = AppError;
+
+    fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
+        let invalid = || AppError::InvalidCursor(s.to_string());
+
+        let mut parts = s.splitn(3, ':');
+        let pinned_order = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+        let micros = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+        let id = parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?;
+        let last_activity_at = DateTime::from_timestamp_micros(micros).ok_or_else(invalid)?;
+
+        Ok(Self {
+            pinned_order,
+            last_activity_at,
+            id,
+        })
+    }
+
+    fn encode_cursor(&self) -> String {
+        format!("{}:{}:{}", self.pinned_order, self.last_activity_at.timestamp_micros(), self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_cursor_encode_and_decode_should_work() {
+        let cursor = ChatCursor {
+            pinned_order: 3,
+            last_activity_at: DateTime::from_timestamp_micros(1731465600123456).unwrap(),
+            id: 42,
+        };
+
+        let decoded = ChatCursor::decode_cursor(&cursor.encode_cursor()).unwrap();
+
+        assert_eq!(decoded, cursor);
+    }
+
+    #[test]
+    fn chat_cursor_decode_invalid_should_fail() {
+        assert!(ChatCursor::decode_cursor("").is_err());
+        assert!(ChatCursor::decode_cursor("1:abc:2").is_err());
+        assert!(ChatCursor::decode_cursor("1:2").is_err());
+    }
+
+    #[test]
+    fn page_size_should_be_clamped() {
+        let config = MessageConfig {
+            default_page_size: 20,
+            max_page_size: 50,
+        };
+
+        assert_eq!(page_size(None, &config), 20);
+        assert_eq!(page_size(Some(0), &config), 1);
+        assert_eq!(page_size(Some(1000), &config), 50);
+    }
+}
@@ -0,0 +1,144 @@
+use async_graphql::{ComplexObject, Context, Enum, SimpleObject, Union};
+use async_graphql::dataloader::DataLoader;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::error::AppError;
+use crate::loader::UserLoader;
+use crate::models::{Sticker, User, UserId};
+
+/// Data of the message kinds whose content is only a readable summary.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Union)]
+pub enum MessagePayload {
+    Location(LocationPayload),
+    Contact(ContactPayload),
+    System(SystemPayload),
+    Sticker(Sticker),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[graphql(complex)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct LocationPayload {
+    #[graphql(skip)]
+    pub(crate) chat_id: i64,
+    #[graphql(skip)]
+    pub(crate) message_id: i64,
+    pub(crate) latitude: f64,
+    pub(crate) longitude: f64,
+    pub(crate) label: Option<String>,
+    /// The sender keeps updating the location until then.
+    pub(crate) live_until: Option<DateTime<Utc>>,
+    pub(crate) updated_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl LocationPayload {
+    async fn is_live(&self) -> bool {
+        self.live_until.is_some_and(|t| t > Utc::now())
+    }
+}
+
+/// A shared user card.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[graphql(complex)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct ContactPayload {
+    #[graphql(skip)]
+    pub(crate) chat_id: i64,
+    #[graphql(skip)]
+    pub(crate) message_id: i64,
+    #[graphql(skip)]
+    pub(crate) user_id: Option<UserId>,
+    /// Name of the user when the card was sent.
+    pub(crate) display_name: String,
+}
+
+#[ComplexObject]
+impl ContactPayload {
+    /// The shared user, `None` once the account is deleted.
+    async fn user(&self, ctx: &Context<'_>) -> Result<Option<User>, AppError> {
+        let Some(user_id) = self.user_id else {
+            return Ok(None);
+        };
+
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        let user = loader.load_one(user_id).await?;
+
+        Ok(user)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, sqlx::Type, Enum, Copy, Eq)]
+#[sqlx(type_name = "system_action", rename_all = "snake_case")]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub enum SystemAction {
+    GroupCreated,
+    GroupRenamed,
+    MemberLeft,
+    OwnerChanged,
+}
+
+/// A change of the chat generated by the server, clients localize it from the action.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[graphql(complex)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct SystemPayload {
+    #[graphql(skip)]
+    pub(crate) chat_id: i64,
+    #[graphql(skip)]
+    pub(crate) message_id: i64,
+    #[graphql(skip)]
+    pub(crate) actor_id: UserId,
+    pub(crate) action: SystemAction,
+    #[graphql(skip)]
+    pub(crate) target_ids: Vec<UserId>,
+    /// The name of a created or renamed group.
+    pub(crate) detail: Option<String>,
+}
+
+#[ComplexObject]
+impl SystemPayload {
+    /// The user who made the change.
+    async fn actor(&self, ctx: &Context<'_>) -> Result<Option<User>, AppError> {
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        let user = loader.load_one(self.actor_id).await?;
+
+        Ok(user)
+    }
+
+    /// The users the change was about, e.g. the members of a new group or the new owner.
+    async fn targets(&self, ctx: &Context<'_>) -> Result<Vec<User>, AppError> {
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        let users = loader.load_many(self.target_ids.iter().cloned()).await?;
+
+        Ok(self.target_ids.iter().filter_map(|id| users.get(id).cloned()).collect())
+    }
+}
+
+/// A system message about to be saved in the transaction of the change.
+#[derive(Debug, Clone)]
+pub(crate) struct NewSystemEvent {
+    pub(crate) actor_id: UserId,
+    pub(crate) action: SystemAction,
+    pub(crate) target_ids: Vec<UserId>,
+    pub(crate) detail: Option<String>,
+}
+
+/// A payload about to be saved with its message.
+#[derive(Debug, Clone)]
+pub(crate) enum NewMessagePayload {
+    Location {
+        latitude: f64,
+        longitude: f64,
+        label: Option<String>,
+        live_until: Option<DateTime<Utc>>,
+    },
+    Contact {
+        user_id: UserId,
+        display_name: String,
+    },
+    Sticker {
+        sticker_id: i64,
+    },
+}
@@ -0,0 +1,24 @@
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::models::{Message, UserId};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct PinnedMessage {
+    #[sqlx(flatten)]
+    pub(crate) message: Message,
+    pub(crate) pinned_by: UserId,
+    pub(crate) pinned_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct StarredMessage {
+    /// Cursor of `myStarredMessages`.
+    pub(crate) star_id: i64,
+    #[sqlx(flatten)]
+    pub(crate) message: Message,
+    pub(crate) starred_at: DateTime<Utc>,
+}
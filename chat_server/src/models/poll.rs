@@ -0,0 +1,98 @@
+use async_graphql::{ComplexObject, Context, SimpleObject};
+use async_graphql::dataloader::DataLoader;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::error::AppError;
+use crate::loader::UserLoader;
+use crate::models::{User, UserId};
+
+/// A poll sent as a message of type poll, the message content is the question.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, SimpleObject)]
+#[graphql(complex)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct Poll {
+    pub(crate) chat_id: i64,
+    pub(crate) message_id: i64,
+    #[graphql(skip)]
+    pub(crate) created_by: UserId,
+    pub(crate) multi_choice: bool,
+    /// Voters are hidden from everyone.
+    pub(crate) anonymous: bool,
+    pub(crate) closes_at: Option<DateTime<Utc>>,
+    pub(crate) closed_at: Option<DateTime<Utc>>,
+    pub(crate) options: Vec<PollOption>,
+}
+
+#[ComplexObject]
+impl Poll {
+    /// Closed by its creator, or past `closesAt`.
+    async fn closed(&self) -> bool {
+        self.is_closed()
+    }
+
+    /// Number of members who voted.
+    async fn voter_count(&self) -> usize {
+        let mut voter_ids: Vec<UserId> = self.options.iter().flat_map(|o| o.voter_ids.iter().cloned()).collect();
+        voter_ids.sort_unstable();
+        voter_ids.dedup();
+
+        voter_ids.len()
+    }
+
+    /// Ids of the options I voted for.
+    async fn my_votes(&self, ctx: &Context<'_>) -> Result<Vec<i32>, AppError> {
+        let user_id = ctx
+            .data::<UserId>()
+            .map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        Ok(self.options.iter().filter(|o| o.voter_ids.contains(user_id)).map(|o| o.option_id).collect())
+    }
+}
+
+impl Poll {
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed_at.is_some() || self.closes_at.is_some_and(|t| t <= Utc::now())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, SimpleObject)]
+#[graphql(complex)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct PollOption {
+    #[graphql(name = "id")]
+    pub(crate) option_id: i32,
+    pub(crate) text: String,
+    pub(crate) vote_count: i32,
+    #[graphql(skip)]
+    pub(crate) voter_ids: Vec<UserId>,
+    #[graphql(skip)]
+    pub(crate) anonymous: bool,
+}
+
+#[ComplexObject]
+impl PollOption {
+    /// Members who voted for the option, empty for anonymous polls.
+    async fn voters(&self, ctx: &Context<'_>) -> Result<Vec<User>, AppError> {
+        if self.anonymous {
+            return Ok(vec![]);
+        }
+
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        let users = loader.load_many(self.voter_ids.iter().cloned()).await?;
+
+        Ok(self.voter_ids.iter().filter_map(|id| users.get(id).cloned()).collect())
+    }
+}
+
+/// Row of the polls table, the options are loaded separately.
+#[derive(Debug, Clone, FromRow)]
+pub(crate) struct PollRow {
+    pub(crate) chat_id: i64,
+    pub(crate) message_id: i64,
+    pub(crate) created_by: UserId,
+    pub(crate) multi_choice: bool,
+    pub(crate) anonymous: bool,
+    pub(crate) closes_at: Option<DateTime<Utc>>,
+    pub(crate) closed_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,22 @@
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// OpenGraph metadata of the first link in a text message.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct LinkPreview {
+    pub(crate) url: String,
+    pub(crate) title: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) image_url: Option<String>,
+    pub(crate) site_name: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub(crate) struct MessageLinkPreview {
+    pub(crate) chat_id: i64,
+    pub(crate) message_id: i64,
+    #[sqlx(flatten)]
+    pub(crate) preview: LinkPreview,
+}
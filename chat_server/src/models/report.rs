@@ -0,0 +1,58 @@
+use async_graphql::{ComplexObject, Context, Enum, SimpleObject};
+use async_graphql::dataloader::DataLoader;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::error::AppError;
+use crate::loader::UserLoader;
+use crate::models::{User, UserId};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, sqlx::Type, Enum, Copy, Eq)]
+#[sqlx(type_name = "report_reason", rename_all = "snake_case")]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    Inappropriate,
+    Impersonation,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, sqlx::Type, Enum, Copy, Eq)]
+#[sqlx(type_name = "report_status", rename_all = "snake_case")]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub enum ReportStatus {
+    Open,
+    Resolved,
+    Dismissed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[graphql(complex)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct Report {
+    pub(crate) id: i64,
+    pub(crate) reporter_id: UserId,
+    pub(crate) target_user_id: UserId,
+    pub(crate) chat_id: Option<i64>,
+    pub(crate) message_id: Option<i64>,
+    pub(crate) reason: ReportReason,
+    pub(crate) description: Option<String>,
+    /// Snapshot of the reported content when the report was made.
+    pub(crate) context: serde_json::Value,
+    pub(crate) status: ReportStatus,
+    pub(crate) created_at: DateTime<Utc>,
+    /// The moderator who resolved or dismissed the report.
+    pub(crate) handled_by: Option<UserId>,
+    pub(crate) handled_at: Option<DateTime<Utc>>,
+}
+
+#[ComplexObject]
+impl Report {
+    async fn target_user(&self, ctx: &Context<'_>) -> Result<User, AppError> {
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        let user = loader.load_one(self.target_user_id).await?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+}
@@ -0,0 +1,33 @@
+use async_graphql::{Enum, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::models::UserId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, sqlx::Type, Enum, Copy, Eq)]
+#[sqlx(type_name = "scheduled_message_status", rename_all = "snake_case")]
+#[serde(rename_all(serialize = "camelCase", deserialize = "camelCase"))]
+pub enum ScheduledMessageStatus {
+    Pending,
+    /// Claimed by the scheduler, which is sending it.
+    Sending,
+    Sent,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct ScheduledMessage {
+    pub(crate) id: i64,
+    pub(crate) chat_id: i64,
+    #[graphql(skip)]
+    pub(crate) user_id: UserId,
+    pub(crate) content: String,
+    pub(crate) send_at: DateTime<Utc>,
+    pub(crate) status: ScheduledMessageStatus,
+    /// The message created when it was sent.
+    pub(crate) message_id: Option<i64>,
+    pub(crate) failure_reason: Option<String>,
+    pub(crate) created_at: DateTime<Utc>,
+}
@@ -0,0 +1,13 @@
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::models::Message;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct MessageSearchHit {
+    #[sqlx(flatten)]
+    pub(crate) message: Message,
+    /// HTML-escaped part of the content around the match, matched words are wrapped in `<mark></mark>`.
+    pub(crate) snippet: String,
+}
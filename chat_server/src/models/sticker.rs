@@ -0,0 +1,53 @@
+use async_graphql::{ComplexObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::models::UserId;
+
+/// A pack of stickers, managed by the server or made of the custom stickers of a user.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, SimpleObject)]
+#[graphql(complex)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct StickerPack {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    /// The user who uploaded the stickers, `None` for server-managed packs.
+    pub(crate) owner_id: Option<UserId>,
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) stickers: Vec<Sticker>,
+}
+
+#[ComplexObject]
+impl StickerPack {
+    async fn custom(&self) -> bool {
+        self.owner_id.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct Sticker {
+    pub(crate) id: i64,
+    pub(crate) pack_id: i64,
+    /// Url of the image uploaded to the file storage.
+    pub(crate) image_url: String,
+    pub(crate) emoji: Option<String>,
+    pub(crate) created_at: DateTime<Utc>,
+}
+
+/// Row of the sticker_packs table, the stickers are loaded separately.
+#[derive(Debug, Clone, FromRow)]
+pub(crate) struct StickerPackRow {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    pub(crate) owner_id: Option<UserId>,
+    pub(crate) created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub(crate) struct MessageSticker {
+    pub(crate) chat_id: i64,
+    pub(crate) message_id: i64,
+    #[sqlx(flatten)]
+    pub(crate) sticker: Sticker,
+}
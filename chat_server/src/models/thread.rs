@@ -0,0 +1,14 @@
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Replies of a thread, keyed by its root message.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, PartialEq, SimpleObject)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct MessageThread {
+    pub(crate) chat_id: i64,
+    pub(crate) root_id: i64,
+    pub(crate) reply_count: i32,
+    pub(crate) last_reply_at: DateTime<Utc>,
+}
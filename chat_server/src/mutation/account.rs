@@ -0,0 +1,51 @@
+use async_graphql::{Context, Object};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::jobs::spawn_data_export;
+use crate::models::{AccountDeletion, DataExport, UserId};
+
+#[derive(Default)]
+pub(crate) struct AccountMutation;
+
+#[Object]
+impl AccountMutation {
+    /// Start building an archive of my data, poll `myDataExports` until it is ready.
+    async fn export_my_data(&self, ctx: &Context<'_>) -> Result<DataExport, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let (export, created) = state.account_repo.create_export(*user_id).await?;
+
+        if created {
+            spawn_data_export(state.clone(), export.id, *user_id);
+        }
+
+        Ok(export)
+    }
+
+    /// Schedule the deletion of my account, it can be cancelled during the grace period.
+    async fn delete_account(
+        &self,
+        ctx: &Context<'_>,
+        password: String,
+    ) -> Result<AccountDeletion, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let user = state.user_repo.find_by_id(*user_id).await?.ok_or(AppError::UserNotFound)?;
+        state.user_repo.verify_password(&user.email, &password).await?;
+
+        let scheduled_at = state.account_repo.schedule_deletion(*user_id, state.config.account.deletion_grace_days).await?;
+
+        Ok(AccountDeletion {
+            scheduled_at,
+        })
+    }
+
+    async fn cancel_account_deletion(&self, ctx: &Context<'_>) -> Result<bool, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.account_repo.cancel_deletion(*user_id).await
+    }
+}
@@ -0,0 +1,199 @@
+use async_graphql::{Context, InputObject, Object};
+use chrono::{DateTime, Utc};
+use jwt_simple::prelude::{Deserialize, Serialize};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::guard::RoleGuard;
+use crate::models::{AdminUser, Message, Report, ReportStatus, Sticker, StickerPack, UserId, UserRole};
+use crate::mutation::sticker::check_image_url;
+use crate::notification::{AccountRestricted, AppEvent, MessageDeleted, Notification};
+
+#[derive(Default)]
+pub(crate) struct AdminMutation;
+
+#[Object]
+impl AdminMutation {
+    #[graphql(guard = "RoleGuard::new(UserRole::Moderator)")]
+    async fn suspend_user(
+        &self,
+        ctx: &Context<'_>,
+        input: SuspendUser,
+    ) -> Result<AdminUser, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        check_outranks(state, *user_id, input.user_id).await?;
+
+        if input.until.is_some_and(|until| until <= Utc::now()) {
+            return Err(AppError::InvalidInput("Suspension must end in the future".to_string()));
+        }
+
+        let user = state.admin_repo.suspend_user(input.user_id, input.until, &input.reason).await?;
+
+        notify_restricted(state, user.id);
+
+        Ok(user)
+    }
+
+    /// Lock the account until the ban is lifted.
+    #[graphql(guard = "RoleGuard::new(UserRole::Moderator)")]
+    async fn ban_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: UserId,
+        #[graphql(validator(min_length = 1, max_length = 256))]
+        reason: String,
+    ) -> Result<AdminUser, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let operator_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        check_outranks(state, *operator_id, user_id).await?;
+
+        let user = state.admin_repo.ban_user(user_id, &reason).await?;
+
+        notify_restricted(state, user.id);
+
+        Ok(user)
+    }
+
+    #[graphql(guard = "RoleGuard::new(UserRole::Moderator)")]
+    async fn unban_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: UserId,
+    ) -> Result<AdminUser, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let operator_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        check_outranks(state, *operator_id, user_id).await?;
+
+        state.admin_repo.unban_user(user_id).await
+    }
+
+    #[graphql(guard = "RoleGuard::new(UserRole::Moderator)")]
+    async fn unsuspend_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: UserId,
+    ) -> Result<AdminUser, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let operator_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        check_outranks(state, *operator_id, user_id).await?;
+
+        state.admin_repo.unsuspend_user(user_id).await
+    }
+
+    #[graphql(guard = "RoleGuard::new(UserRole::Admin)")]
+    async fn set_user_role(
+        &self,
+        ctx: &Context<'_>,
+        user_id: UserId,
+        role: UserRole,
+    ) -> Result<AdminUser, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let operator_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        if user_id == *operator_id {
+            return Err(AppError::InvalidInput("Can not change your own role".to_string()));
+        }
+
+        state.admin_repo.set_role(user_id, role).await
+    }
+
+    #[graphql(guard = "RoleGuard::new(UserRole::Moderator)")]
+    async fn delete_message(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        message_id: i64,
+    ) -> Result<Message, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+
+        let messages = state.admin_repo.delete_message(chat_id, message_id).await?;
+
+        for message in &messages {
+            let _ = state.sender.send(Notification {
+                event: AppEvent::MessageDeleted(MessageDeleted {
+                    data: message.clone(),
+                }),
+            });
+        }
+
+        Ok(messages[0].clone())
+    }
+
+    /// Resolve or dismiss a report.
+    #[graphql(guard = "RoleGuard::new(UserRole::Moderator)")]
+    async fn handle_report(
+        &self,
+        ctx: &Context<'_>,
+        report_id: i64,
+        status: ReportStatus,
+    ) -> Result<Report, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.admin_repo.handle_report(report_id, *user_id, status).await
+    }
+
+    /// Create a server-managed sticker pack, which every user has.
+    #[graphql(guard = "RoleGuard::new(UserRole::Admin)")]
+    async fn create_sticker_pack(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(min_length = 1, max_length = 64))]
+        name: String,
+    ) -> Result<StickerPack, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+
+        state.sticker_repo.create_server_pack(&name).await
+    }
+
+    /// Add an image uploaded to the file storage to a server-managed sticker pack.
+    #[graphql(guard = "RoleGuard::new(UserRole::Admin)")]
+    async fn add_pack_sticker(
+        &self,
+        ctx: &Context<'_>,
+        pack_id: i64,
+        image_url: String,
+        #[graphql(validator(max_length = 16))]
+        emoji: Option<String>,
+    ) -> Result<Sticker, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+
+        check_image_url(state, &image_url)?;
+
+        state.sticker_repo.add_server_sticker(pack_id, &image_url, emoji.as_deref()).await
+    }
+}
+
+/// Close the open sessions of the user right away.
+fn notify_restricted(state: &AppState, user_id: UserId) {
+    let _ = state.sender.send(Notification {
+        event: AppEvent::AccountRestricted(AccountRestricted {
+            user_id,
+        }),
+    });
+}
+
+/// Operators can only act on users with a lower role than their own.
+async fn check_outranks(state: &AppState, operator_id: UserId, user_id: UserId) -> Result<(), AppError> {
+    let operator_role = state.user_repo.get_role(operator_id).await?.ok_or(AppError::Forbidden)?;
+    let user_role = state.user_repo.get_role(user_id).await?.ok_or(AppError::UserNotFound)?;
+
+    if user_role >= operator_role {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, InputObject)]
+struct SuspendUser {
+    user_id: UserId,
+    /// The user is suspended indefinitely when it is null, until an operator lifts it.
+    until: Option<DateTime<Utc>>,
+    #[graphql(validator(min_length = 1, max_length = 256))]
+    reason: String,
+}
@@ -1,7 +1,11 @@
 use async_graphql::{Context, Object};
+use chrono::{DateTime, Utc};
 use crate::app_state::AppState;
 use crate::error::AppError;
-use crate::models::{Chat, UserId};
+use crate::models::{Chat, ChatMemberSettings, UserId};
+
+const MIN_MESSAGE_TTL_SECONDS: i32 = 60;
+const MAX_MESSAGE_TTL_SECONDS: i32 = 365 * 24 * 60 * 60;
 
 #[derive(Default)]
 pub(crate) struct ChatMutation;
@@ -20,6 +24,42 @@ impl ChatMutation {
         state.chat_repo.update_chat_name(name, chat_id, *user_id).await
     }
 
+    /// Only the owner can forbid links in the chat.
+    async fn set_chat_block_links(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        block_links: bool,
+    ) -> Result<bool, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.chat_repo.set_block_links(chat_id, *user_id, block_links).await
+    }
+
+    /// Turn disappearing messages on with a timer in seconds, or off with `null`.
+    /// Only messages sent afterwards disappear.
+    async fn set_chat_message_ttl(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        ttl_seconds: Option<i32>,
+    ) -> Result<Chat, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        if let Some(ttl) = ttl_seconds {
+            if !(MIN_MESSAGE_TTL_SECONDS..=MAX_MESSAGE_TTL_SECONDS).contains(&ttl) {
+                return Err(AppError::InvalidInput(format!(
+                    "Message timer must be between {} and {} seconds",
+                    MIN_MESSAGE_TTL_SECONDS, MAX_MESSAGE_TTL_SECONDS
+                )));
+            }
+        }
+
+        state.chat_repo.set_message_ttl(chat_id, *user_id, ttl_seconds).await
+    }
+
     async fn drop_chat(
         &self,
         ctx: &Context<'_>,
@@ -58,4 +98,54 @@ impl ChatMutation {
 
         Ok(res)
     }
+
+    /// Mute the chat until the given time, pass null to unmute.
+    async fn mute_chat(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<ChatMemberSettings, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.chat_repo.set_muted_until(chat_id, *user_id, until).await
+    }
+
+    async fn pin_chat(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        pinned: bool,
+    ) -> Result<ChatMemberSettings, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.chat_repo.set_pinned(chat_id, *user_id, pinned).await
+    }
+
+    async fn archive_chat(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        archived: bool,
+    ) -> Result<ChatMemberSettings, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.chat_repo.set_archived(chat_id, *user_id, archived).await
+    }
+
+    /// Set my nickname in a group chat, pass null to clear it.
+    async fn set_chat_nickname(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        nickname: Option<String>,
+    ) -> Result<ChatMemberSettings, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.chat_repo.set_nickname(chat_id, *user_id, nickname).await
+    }
 }
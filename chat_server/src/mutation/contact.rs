@@ -0,0 +1,117 @@
+use async_graphql::{Context, Object};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::models::{FriendRequest, FriendRequestStatus, UserId};
+use crate::notification::{AppEvent, BlockChanged, FriendRequestAccepted, FriendRequestReceived, Notification};
+
+#[derive(Default)]
+pub(crate) struct ContactMutation;
+
+#[Object]
+impl ContactMutation {
+    /// Send a friend request, if the user already sent me one it is accepted.
+    async fn send_friend_request(
+        &self,
+        ctx: &Context<'_>,
+        user_id: UserId,
+        #[graphql(validator(max_length = 256))]
+        message: Option<String>,
+    ) -> Result<FriendRequest, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let current_user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let request = state.contact_repo.send_request(*current_user_id, user_id, message).await?;
+
+        let event = match request.status {
+            FriendRequestStatus::Accepted => AppEvent::FriendRequestAccepted(FriendRequestAccepted { data: request.clone() }),
+            _ => AppEvent::FriendRequestReceived(FriendRequestReceived { data: request.clone() }),
+        };
+        let _ = state.sender.send(Notification { event });
+
+        Ok(request)
+    }
+
+    async fn accept_friend_request(
+        &self,
+        ctx: &Context<'_>,
+        request_id: i64,
+    ) -> Result<FriendRequest, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let request = state.contact_repo.accept_request(request_id, *user_id).await?;
+
+        let event = AppEvent::FriendRequestAccepted(FriendRequestAccepted { data: request.clone() });
+        let _ = state.sender.send(Notification { event });
+
+        Ok(request)
+    }
+
+    async fn reject_friend_request(
+        &self,
+        ctx: &Context<'_>,
+        request_id: i64,
+    ) -> Result<FriendRequest, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.contact_repo.reject_request(request_id, *user_id).await
+    }
+
+    /// Blocked users can't start private chats with me, and I don't receive their messages.
+    async fn block_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: UserId,
+    ) -> Result<bool, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let current_user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let blocked = state.contact_repo.block_user(*current_user_id, user_id).await?;
+
+        if blocked {
+            notify_block_changed(state, *current_user_id, user_id, true);
+        }
+
+        Ok(blocked)
+    }
+
+    async fn unblock_user(
+        &self,
+        ctx: &Context<'_>,
+        user_id: UserId,
+    ) -> Result<bool, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let current_user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let unblocked = state.contact_repo.unblock_user(*current_user_id, user_id).await?;
+
+        if unblocked {
+            notify_block_changed(state, *current_user_id, user_id, false);
+        }
+
+        Ok(unblocked)
+    }
+
+    async fn remove_contact(
+        &self,
+        ctx: &Context<'_>,
+        contact_id: UserId,
+    ) -> Result<bool, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.contact_repo.remove_contact(*user_id, contact_id).await
+    }
+}
+
+/// Open subscriptions of the user keep their block list in memory, this keeps it up to date.
+fn notify_block_changed(state: &AppState, user_id: UserId, blocked_id: UserId, blocked: bool) {
+    let _ = state.sender.send(Notification {
+        event: AppEvent::BlockChanged(BlockChanged {
+            user_id,
+            blocked_id,
+            blocked,
+        }),
+    });
+}
@@ -0,0 +1,60 @@
+use async_graphql::{Context, Object};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::models::{Draft, SessionId, UserId};
+use crate::notification::{AppEvent, DraftUpdated, Notification};
+
+#[derive(Default)]
+pub(crate) struct DraftMutation;
+
+#[Object]
+impl DraftMutation {
+    /// Save my draft in a chat, an empty content clears it.
+    async fn save_draft(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        content: String,
+    ) -> Result<Option<Draft>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        if content.chars().count() > state.config.message_filter.max_length {
+            return Err(AppError::InvalidInput("Draft is too long".to_string()));
+        }
+
+        let draft = state.chat_repo.save_draft(chat_id, *user_id, &content).await?;
+
+        notify_draft_updated(ctx, chat_id, *user_id, draft.clone());
+
+        Ok(draft)
+    }
+
+    async fn clear_draft(&self, ctx: &Context<'_>, chat_id: i64) -> Result<bool, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let cleared = state.chat_repo.clear_draft(chat_id, *user_id).await?;
+
+        if cleared {
+            notify_draft_updated(ctx, chat_id, *user_id, None);
+        }
+
+        Ok(cleared)
+    }
+}
+
+/// Tell the other sessions of the user that the draft changed.
+pub(crate) fn notify_draft_updated(ctx: &Context<'_>, chat_id: i64, user_id: UserId, draft: Option<Draft>) {
+    let state = ctx.data_unchecked::<AppState>();
+    let session_id = ctx.data::<SessionId>().ok().map(|s| s.0.clone());
+
+    let _ = state.sender.send(Notification {
+        event: AppEvent::DraftUpdated(DraftUpdated {
+            chat_id,
+            data: draft,
+            user_id,
+            session_id,
+        }),
+    });
+}
@@ -1,8 +1,20 @@
 use async_graphql::{Context, InputObject, Object};
+use chrono::{Duration, Utc};
 use jwt_simple::prelude::{Deserialize, Serialize};
 use crate::app_state::AppState;
 use crate::error::AppError;
-use crate::models::{Message, MessageType, UserId};
+use crate::filter::OutgoingMessage;
+use crate::jobs::spawn_link_preview;
+use crate::mutation::draft::notify_draft_updated;
+use crate::models::{Message, MessageType, NewMessagePayload, PinnedMessage, StarredMessage, UserId};
+use crate::repository::ForwardCopy;
+use crate::notification::{AppEvent, Mentioned, MessagePinned, MessageUnpinned, MessageUpdated, Notification, ThreadUpdated};
+
+const MAX_FORWARD_MESSAGES: usize = 100;
+const MAX_FORWARD_TARGETS: usize = 20;
+const MAX_LOCATION_LABEL_CHARS: usize = 200;
+const MIN_LIVE_LOCATION_SECONDS: i64 = 60;
+const MAX_LIVE_LOCATION_SECONDS: i64 = 8 * 60 * 60;
 
 #[derive(Default)]
 pub(crate) struct MessageMutation;
@@ -17,14 +29,325 @@ impl MessageMutation {
         let state = ctx.data_unchecked::<AppState>();
         let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
 
-        let message = state.message_repo.create_message(input.chat_id, *user_id, MessageType::Text, input.content).await?;
+        let mut message = OutgoingMessage {
+            chat_id: input.chat_id,
+            user_id: *user_id,
+            r#type: MessageType::Text,
+            content: input.content,
+            thread_root_id: input.thread_root_id,
+        };
+        state.message_filters.apply(&mut message).await?;
+
+        let (message, thread) = state.message_repo
+            .create_message(message, None, &input.mentions, input.mention_all)
+            .await?;
+
+        if let Some(thread) = thread {
+            let _ = state.sender.send(Notification {
+                event: AppEvent::ThreadUpdated(ThreadUpdated {
+                    data: thread,
+                    reply: message.clone(),
+                }),
+            });
+        }
+
+        if input.mention_all || !input.mentions.is_empty() {
+            let user_ids = if input.mention_all {
+                state.chat_repo.get_member_ids(message.chat_id).await?
+            } else {
+                input.mentions
+            };
+
+            let _ = state.sender.send(Notification {
+                event: AppEvent::Mentioned(Mentioned {
+                    data: message.clone(),
+                    user_ids,
+                }),
+            });
+        }
+
+        spawn_link_preview(state.clone(), message.clone());
+
+        // the draft is sent, so it is gone on every device
+        if state.chat_repo.clear_draft(message.chat_id, *user_id).await? {
+            notify_draft_updated(ctx, message.chat_id, *user_id, None);
+        }
+
+        Ok(message)
+    }
+
+    /// Copy messages of a chat into other chats I am a member of, in the order they were sent.
+    async fn forward_messages(
+        &self,
+        ctx: &Context<'_>,
+        source_chat_id: i64,
+        message_ids: Vec<i64>,
+        target_chat_ids: Vec<i64>,
+    ) -> Result<Vec<Message>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let mut message_ids = message_ids;
+        message_ids.sort_unstable();
+        message_ids.dedup();
+
+        let mut target_chat_ids = target_chat_ids;
+        target_chat_ids.sort_unstable();
+        target_chat_ids.dedup();
+
+        if message_ids.is_empty() || message_ids.len() > MAX_FORWARD_MESSAGES {
+            return Err(AppError::InvalidInput(format!("Forward 1 to {} messages at a time", MAX_FORWARD_MESSAGES)));
+        }
+        if target_chat_ids.is_empty() || target_chat_ids.len() > MAX_FORWARD_TARGETS {
+            return Err(AppError::InvalidInput(format!("Forward to 1 to {} chats at a time", MAX_FORWARD_TARGETS)));
+        }
+
+        let sources = state.message_repo.get_forward_sources(source_chat_id, &message_ids, *user_id).await?;
+
+        if sources.iter().any(|s| matches!(s.message.r#type, MessageType::Poll | MessageType::Location | MessageType::Contact | MessageType::Sticker | MessageType::System)) {
+            return Err(AppError::InvalidInput("Polls, locations, contact cards, stickers and system messages can not be forwarded".to_string()));
+        }
+
+        let mut copies = Vec::with_capacity(sources.len() * target_chat_ids.len());
+        for chat_id in target_chat_ids {
+            for source in &sources {
+                let mut message = OutgoingMessage {
+                    chat_id,
+                    user_id: *user_id,
+                    r#type: source.message.r#type,
+                    content: source.message.content.clone(),
+                    thread_root_id: None,
+                };
+                state.message_filters.apply(&mut message).await?;
+
+                copies.push(ForwardCopy {
+                    message,
+                    source: source.clone(),
+                });
+            }
+        }
+
+        let messages = state.message_repo.forward_messages(copies).await?;
+
+        for message in &messages {
+            spawn_link_preview(state.clone(), message.clone());
+        }
+
+        Ok(messages)
+    }
+
+    /// Share a location, live for `live_seconds` if given, during which `updateLiveLocation` moves it.
+    async fn send_location(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        latitude: f64,
+        longitude: f64,
+        label: Option<String>,
+        live_seconds: Option<i64>,
+    ) -> Result<Message, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        check_coordinates(latitude, longitude)?;
+
+        let label = label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+        if label.as_ref().is_some_and(|l| l.chars().count() > MAX_LOCATION_LABEL_CHARS) {
+            return Err(AppError::InvalidInput(format!("Location label is longer than {} chars", MAX_LOCATION_LABEL_CHARS)));
+        }
+
+        let live_until = match live_seconds {
+            Some(seconds) if (MIN_LIVE_LOCATION_SECONDS..=MAX_LIVE_LOCATION_SECONDS).contains(&seconds) => {
+                Some(Utc::now() + Duration::seconds(seconds))
+            }
+            Some(_) => {
+                return Err(AppError::InvalidInput(format!(
+                    "Live location lasts {} to {} seconds",
+                    MIN_LIVE_LOCATION_SECONDS, MAX_LIVE_LOCATION_SECONDS
+                )));
+            }
+            None => None,
+        };
+
+        // the content is what chat lists show as the latest message
+        let content = match (&label, live_until) {
+            (Some(label), _) => format!("Location: {}", label),
+            (None, Some(_)) => "Live location".to_string(),
+            (None, None) => "Location".to_string(),
+        };
+
+        let mut message = OutgoingMessage {
+            chat_id,
+            user_id: *user_id,
+            r#type: MessageType::Location,
+            content,
+            thread_root_id: None,
+        };
+        state.message_filters.apply(&mut message).await?;
+
+        let payload = NewMessagePayload::Location {
+            latitude,
+            longitude,
+            label,
+            live_until,
+        };
+
+        let (message, _) = state.message_repo.create_message(message, Some(payload), &[], false).await?;
+
+        Ok(message)
+    }
+
+    /// Move my live location, members get a `MessageUpdated` event.
+    async fn update_live_location(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        message_id: i64,
+        latitude: f64,
+        longitude: f64,
+    ) -> Result<Message, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        check_coordinates(latitude, longitude)?;
+
+        let message = state.message_repo
+            .update_live_location(chat_id, message_id, *user_id, latitude, longitude)
+            .await?;
+
+        let _ = state.sender.send(Notification {
+            event: AppEvent::MessageUpdated(MessageUpdated {
+                data: message.clone(),
+            }),
+        });
 
         Ok(message)
     }
+
+    /// Share the card of a user I could find in the user search.
+    async fn send_contact(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        contact_user_id: UserId,
+    ) -> Result<Message, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let contact = state.user_repo
+            .find_visible_user(*user_id, contact_user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        let mut message = OutgoingMessage {
+            chat_id,
+            user_id: *user_id,
+            r#type: MessageType::Contact,
+            content: format!("Contact: {}", contact.fullname),
+            thread_root_id: None,
+        };
+        state.message_filters.apply(&mut message).await?;
+
+        let payload = NewMessagePayload::Contact {
+            user_id: contact.id,
+            display_name: contact.fullname,
+        };
+
+        let (message, _) = state.message_repo.create_message(message, Some(payload), &[], false).await?;
+
+        Ok(message)
+    }
+
+    /// In group chats only the owner can pin messages.
+    async fn pin_message(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        message_id: i64,
+    ) -> Result<PinnedMessage, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let pinned = state.message_repo.pin_message(chat_id, message_id, *user_id).await?;
+
+        let _ = state.sender.send(Notification {
+            event: AppEvent::MessagePinned(MessagePinned {
+                data: pinned.clone(),
+            }),
+        });
+
+        Ok(pinned)
+    }
+
+    async fn unpin_message(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        message_id: i64,
+    ) -> Result<bool, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let unpinned = state.message_repo.unpin_message(chat_id, message_id, *user_id).await?;
+
+        if unpinned {
+            let _ = state.sender.send(Notification {
+                event: AppEvent::MessageUnpinned(MessageUnpinned {
+                    chat_id,
+                    message_id,
+                }),
+            });
+        }
+
+        Ok(unpinned)
+    }
+
+    async fn star_message(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        message_id: i64,
+    ) -> Result<StarredMessage, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.message_repo.star_message(chat_id, message_id, *user_id).await
+    }
+
+    async fn unstar_message(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        message_id: i64,
+    ) -> Result<bool, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.message_repo.unstar_message(chat_id, message_id, *user_id).await
+    }
+}
+
+fn check_coordinates(latitude: f64, longitude: f64) -> Result<(), AppError> {
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(AppError::InvalidInput("Invalid coordinates".to_string()));
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, InputObject)]
 struct CreateMessage {
     chat_id: i64,
     content: String,
+    /// Ids of the members mentioned with `@user`.
+    #[graphql(default)]
+    #[serde(default)]
+    mentions: Vec<UserId>,
+    /// `@all`, only the owner of a group chat can use it.
+    #[graphql(default)]
+    #[serde(default)]
+    mention_all: bool,
+    /// Reply in the thread of this message instead of the main timeline, group chats only.
+    #[serde(default)]
+    thread_root_id: Option<i64>,
 }
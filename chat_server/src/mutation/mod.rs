@@ -1,11 +1,27 @@
 use async_graphql::MergedObject;
+use crate::mutation::account::AccountMutation;
+use crate::mutation::admin::AdminMutation;
 use crate::mutation::chat::ChatMutation;
+use crate::mutation::contact::ContactMutation;
+use crate::mutation::draft::DraftMutation;
 use crate::mutation::message::MessageMutation;
+use crate::mutation::poll::PollMutation;
+use crate::mutation::report::ReportMutation;
+use crate::mutation::schedule::ScheduleMutation;
+use crate::mutation::sticker::StickerMutation;
 use crate::mutation::user::UserMutation;
 
 mod chat;
 mod message;
 mod user;
+mod contact;
+mod report;
+mod account;
+mod admin;
+mod draft;
+mod schedule;
+mod poll;
+mod sticker;
 
 #[derive(MergedObject, Default)]
-pub(crate) struct MutationRoot(UserMutation, ChatMutation, MessageMutation);
+pub(crate) struct MutationRoot(UserMutation, ChatMutation, MessageMutation, ContactMutation, ReportMutation, AccountMutation, AdminMutation, DraftMutation, ScheduleMutation, PollMutation, StickerMutation);
@@ -0,0 +1,121 @@
+use async_graphql::{Context, Object};
+use chrono::{DateTime, Utc};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::filter::OutgoingMessage;
+use crate::models::{Message, MessageType, Poll, UserId};
+use crate::notification::{AppEvent, Notification, PollUpdated};
+use crate::repository::NewPoll;
+
+const MIN_POLL_OPTIONS: usize = 2;
+const MAX_POLL_OPTIONS: usize = 10;
+
+#[derive(Default)]
+pub(crate) struct PollMutation;
+
+#[Object]
+impl PollMutation {
+    /// Send a poll to a group chat. It closes at `closes_at` if given, or when its creator closes it.
+    async fn create_poll(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        question: String,
+        options: Vec<String>,
+        #[graphql(default)] multi_choice: bool,
+        #[graphql(default)] anonymous: bool,
+        closes_at: Option<DateTime<Utc>>,
+    ) -> Result<Message, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let options: Vec<String> = options.into_iter().map(|o| o.trim().to_string()).collect();
+        if question.trim().is_empty() || options.iter().any(|o| o.is_empty()) {
+            return Err(AppError::InvalidInput("Poll question and options can not be empty".to_string()));
+        }
+        if !(MIN_POLL_OPTIONS..=MAX_POLL_OPTIONS).contains(&options.len()) {
+            return Err(AppError::InvalidInput(format!("A poll has {} to {} options", MIN_POLL_OPTIONS, MAX_POLL_OPTIONS)));
+        }
+        if closes_at.is_some_and(|t| t <= Utc::now()) {
+            return Err(AppError::InvalidInput("Close time must be in the future".to_string()));
+        }
+
+        // the question and every option go through the message filters
+        let mut texts = Vec::with_capacity(options.len() + 1);
+        for content in std::iter::once(question).chain(options) {
+            let mut message = OutgoingMessage {
+                chat_id,
+                user_id: *user_id,
+                r#type: MessageType::Poll,
+                content,
+                thread_root_id: None,
+            };
+            state.message_filters.apply(&mut message).await?;
+            texts.push(message.content);
+        }
+        let question = texts.remove(0);
+
+        let poll = NewPoll {
+            chat_id,
+            question,
+            options: texts,
+            multi_choice,
+            anonymous,
+            closes_at,
+        };
+
+        state.poll_repo.create_poll(*user_id, poll).await
+    }
+
+    /// Replace my votes in a poll, an empty list retracts them.
+    async fn vote_poll(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        message_id: i64,
+        option_ids: Vec<i32>,
+    ) -> Result<Poll, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let mut option_ids = option_ids;
+        option_ids.sort_unstable();
+        option_ids.dedup();
+
+        let poll = state.poll_repo.vote(chat_id, message_id, *user_id, &option_ids).await?;
+
+        notify_poll_updated(state, poll.clone()).await?;
+
+        Ok(poll)
+    }
+
+    /// Only the creator of the poll or the owner of the chat can close it.
+    async fn close_poll(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        message_id: i64,
+    ) -> Result<Poll, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let poll = state.poll_repo.close_poll(chat_id, message_id, *user_id).await?;
+
+        notify_poll_updated(state, poll.clone()).await?;
+
+        Ok(poll)
+    }
+}
+
+async fn notify_poll_updated(state: &AppState, poll: Poll) -> Result<(), AppError> {
+    let member_ids = state.chat_repo.get_member_ids(poll.chat_id).await?;
+
+    let _ = state.sender.send(Notification {
+        event: AppEvent::PollUpdated(PollUpdated {
+            data: poll,
+            member_ids,
+        }),
+    });
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use async_graphql::{Context, InputObject, Object};
+use jwt_simple::prelude::{Deserialize, Serialize};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::models::{Report, ReportReason, UserId};
+
+#[derive(Default)]
+pub(crate) struct ReportMutation;
+
+#[Object]
+impl ReportMutation {
+    async fn report_user(
+        &self,
+        ctx: &Context<'_>,
+        input: ReportUser,
+    ) -> Result<Report, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.report_repo.report_user(*user_id, input.user_id, input.chat_id, input.reason, input.description).await
+    }
+
+    async fn report_message(
+        &self,
+        ctx: &Context<'_>,
+        input: ReportMessage,
+    ) -> Result<Report, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.report_repo.report_message(*user_id, input.chat_id, input.message_id, input.reason, input.description).await
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, InputObject)]
+struct ReportUser {
+    user_id: UserId,
+    /// The chat in which the user behaved badly, if any.
+    chat_id: Option<i64>,
+    reason: ReportReason,
+    #[graphql(validator(max_length = 1000))]
+    description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, InputObject)]
+struct ReportMessage {
+    chat_id: i64,
+    message_id: i64,
+    reason: ReportReason,
+    #[graphql(validator(max_length = 1000))]
+    description: Option<String>,
+}
@@ -0,0 +1,54 @@
+use async_graphql::{Context, Object};
+use chrono::{DateTime, Duration, Utc};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::filter::OutgoingMessage;
+use crate::models::{MessageType, ScheduledMessage, UserId};
+
+const MAX_SCHEDULE_DAYS: i64 = 365;
+
+#[derive(Default)]
+pub(crate) struct ScheduleMutation;
+
+#[Object]
+impl ScheduleMutation {
+    /// Send a text message to a chat at `send_at`. The message filters run now and again when it is sent.
+    async fn schedule_message(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        content: String,
+        send_at: DateTime<Utc>,
+    ) -> Result<ScheduledMessage, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let now = Utc::now();
+        if send_at <= now {
+            return Err(AppError::InvalidInput("Send time must be in the future".to_string()));
+        }
+        if send_at > now + Duration::days(MAX_SCHEDULE_DAYS) {
+            return Err(AppError::InvalidInput(format!("Messages can be scheduled at most {} days ahead", MAX_SCHEDULE_DAYS)));
+        }
+
+        let mut message = OutgoingMessage {
+            chat_id,
+            user_id: *user_id,
+            r#type: MessageType::Text,
+            content,
+            thread_root_id: None,
+        };
+        state.message_filters.apply(&mut message).await?;
+
+        state.schedule_repo
+            .create_scheduled_message(chat_id, *user_id, message.content, send_at)
+            .await
+    }
+
+    async fn cancel_scheduled_message(&self, ctx: &Context<'_>, id: i64) -> Result<ScheduledMessage, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.schedule_repo.cancel_scheduled_message(id, *user_id).await
+    }
+}
@@ -0,0 +1,96 @@
+use async_graphql::{Context, Object};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::filter::OutgoingMessage;
+use crate::models::{Message, MessageType, NewMessagePayload, Sticker, StickerPack, UserId};
+use crate::utils::is_storage_url;
+
+#[derive(Default)]
+pub(crate) struct StickerMutation;
+
+#[Object]
+impl StickerMutation {
+    /// Send a sticker of a server-managed pack, my own pack or a pack I added.
+    /// The message only references it so the image is not copied.
+    async fn send_sticker(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        sticker_id: i64,
+    ) -> Result<Message, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let sticker = state.sticker_repo.get_sticker(*user_id, sticker_id).await?.ok_or(AppError::StickerNotFound)?;
+
+        // the content is what chat lists show as the latest message
+        let content = match &sticker.emoji {
+            Some(emoji) => format!("{} Sticker", emoji),
+            None => "Sticker".to_string(),
+        };
+
+        let mut message = OutgoingMessage {
+            chat_id,
+            user_id: *user_id,
+            r#type: MessageType::Sticker,
+            content,
+            thread_root_id: None,
+        };
+        state.message_filters.apply(&mut message).await?;
+
+        let payload = NewMessagePayload::Sticker {
+            sticker_id: sticker.id,
+        };
+
+        let (message, _) = state.message_repo.create_message(message, Some(payload), &[], false).await?;
+
+        Ok(message)
+    }
+
+    /// Add the custom pack of another user, e.g. of a sticker received in a chat, to my sticker packs.
+    async fn add_sticker_pack(
+        &self,
+        ctx: &Context<'_>,
+        pack_id: i64,
+    ) -> Result<StickerPack, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.sticker_repo.add_sticker_pack(*user_id, pack_id).await
+    }
+
+    async fn remove_sticker_pack(
+        &self,
+        ctx: &Context<'_>,
+        pack_id: i64,
+    ) -> Result<bool, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.sticker_repo.remove_sticker_pack(*user_id, pack_id).await
+    }
+
+    /// Add an image uploaded to the file storage to my custom stickers.
+    async fn add_custom_sticker(
+        &self,
+        ctx: &Context<'_>,
+        image_url: String,
+        #[graphql(validator(max_length = 16))]
+        emoji: Option<String>,
+    ) -> Result<Sticker, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        check_image_url(state, &image_url)?;
+
+        state.sticker_repo.add_custom_sticker(*user_id, &image_url, emoji.as_deref()).await
+    }
+}
+
+pub(crate) fn check_image_url(state: &AppState, image_url: &str) -> Result<(), AppError> {
+    if !is_storage_url(image_url, &state.config.storage) {
+        return Err(AppError::InvalidInput("Sticker image must be a file uploaded to the storage".to_string()));
+    }
+
+    Ok(())
+}
@@ -4,8 +4,9 @@ use tracing_subscriber::filter::combinator::Not;
 use crate::app_state::AppState;
 use crate::config::AppConfig;
 use crate::error::AppError;
-use crate::models::{User, UserId};
-use crate::notification::{AppEvent, Notification, QRCodeCancel, QRCodeConfirmed, QRCodeScanned};
+use crate::models::{PrivacySettings, User, UserId, Visibility};
+use crate::notification::{AppEvent, Notification, QRCodeCancel, QRCodeConfirmed, QRCodeScanned, UserProfileChanged};
+use crate::utils::is_storage_url;
 
 #[derive(Default)]
 pub(crate) struct UserMutation;
@@ -72,6 +73,8 @@ impl UserMutation {
         let state = ctx.data_unchecked::<AppState>();
         let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
 
+        state.user_repo.check_restriction(*user_id).await?;
+
         let token = state.ek.sign(*user_id, state.config.jwt.period_seconds)?;
 
         let event = AppEvent::QRCodeConfirmed(QRCodeConfirmed {
@@ -105,12 +108,89 @@ impl UserMutation {
                     user_id: u.id
                 })
             },
+            Err(e @ (AppError::AccountSuspended { .. } | AppError::AccountBanned { .. })) => Err(e),
             Err(_) => {
                 Err(AppError::UserNotFound)
             }
         }
     }
 
+    async fn update_profile(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateProfile,
+    ) -> anyhow::Result<User, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let fullname = input.fullname.map(|f| f.trim().to_string());
+        if matches!(&fullname, Some(f) if f.is_empty()) {
+            return Err(AppError::InvalidInput("Fullname can not be empty".to_string()));
+        }
+
+        if let Some(avatar) = &input.avatar {
+            if !avatar.is_empty() && !is_storage_url(avatar, &state.config.storage) {
+                return Err(AppError::InvalidInput("Avatar must be a file uploaded to the storage".to_string()));
+            }
+        }
+
+        let user = state.user_repo.update_profile(*user_id, fullname, input.avatar, input.bio, input.status_text).await?;
+
+        let event = AppEvent::UserProfileChanged(UserProfileChanged { data: user.clone() });
+        let _ = state.sender.send(Notification { event });
+
+        Ok(user)
+    }
+
+    /// Send a verification code to the new email, then call `change_email` with it.
+    async fn send_email_change_code(
+        &self,
+        ctx: &Context<'_>,
+        input: SendEmail,
+    ) -> anyhow::Result<MessageOutput, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let _ = state.user_repo.send_email_change_code(*user_id, &input.email).await?;
+
+        Ok(MessageOutput {
+            message: "Send success.".to_string(),
+        })
+    }
+
+    async fn change_email(
+        &self,
+        ctx: &Context<'_>,
+        input: ChangeEmail,
+    ) -> anyhow::Result<User, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let user = state.user_repo.change_email(*user_id, &input.email, &input.code).await?;
+
+        let event = AppEvent::UserProfileChanged(UserProfileChanged { data: user.clone() });
+        let _ = state.sender.send(Notification { event });
+
+        Ok(user)
+    }
+
+    async fn update_privacy_settings(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdatePrivacySettings,
+    ) -> anyhow::Result<PrivacySettings, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.user_repo.update_privacy_settings(
+            *user_id,
+            input.email_discoverable,
+            input.email_visibility,
+            input.avatar_visibility,
+            input.forward_attribution,
+        ).await
+    }
+
     async fn send_email(
         &self,
         ctx: &Context<'_>,
@@ -140,6 +220,34 @@ struct CreateUser {
     fullname: String,
 }
 
+/// Fields left null are unchanged, empty strings clear avatar, bio and status text.
+#[derive(Debug, Clone, Deserialize, Serialize, InputObject)]
+struct UpdateProfile {
+    #[graphql(validator(max_length = 64))]
+    fullname: Option<String>,
+    /// Url of the avatar uploaded to the file storage.
+    #[graphql(validator(max_length = 256))]
+    avatar: Option<String>,
+    #[graphql(validator(max_length = 256))]
+    bio: Option<String>,
+    #[graphql(validator(max_length = 64))]
+    status_text: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, InputObject)]
+struct ChangeEmail {
+    email: String,
+    code: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, InputObject)]
+struct UpdatePrivacySettings {
+    email_discoverable: Option<bool>,
+    email_visibility: Option<Visibility>,
+    avatar_visibility: Option<Visibility>,
+    forward_attribution: Option<bool>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, InputObject)]
 struct SigninUser {
     email: String,
@@ -9,7 +9,7 @@ use crate::app_state::AppState;
 use crate::config::AppConfig;
 use crate::error::AppError;
 use crate::handler::MutationType;
-use crate::models::{Chat, Message};
+use crate::models::{Chat, Draft, FriendRequest, Message, MessageThread, PinnedMessage, Poll, User, UserId};
 
 pub(crate) async fn setup_pg_listener(state: AppState) -> anyhow::Result<()> {
     let config = &state.config;;
@@ -47,11 +47,26 @@ pub(crate) enum AppEvent {
     CreatedChat(CreatedChat),
     ChatOwnerChanged(ChatOwnerChanged),
     ChatNameChanged(ChatNameChanged),
+    ChatMessageTtlChanged(ChatMessageTtlChanged),
     ChatDeleted(ChatDeleted),
     NewMessage(Message),
+    MessageDeleted(MessageDeleted),
+    MessageUpdated(MessageUpdated),
+    MessagesExpired(MessagesExpired),
+    MessagePinned(MessagePinned),
+    Mentioned(Mentioned),
+    MessageUnpinned(MessageUnpinned),
+    ThreadUpdated(ThreadUpdated),
+    PollUpdated(PollUpdated),
     QRCodeScanned(QRCodeScanned),
     QRCodeCancel(QRCodeCancel),
     QRCodeConfirmed(QRCodeConfirmed),
+    FriendRequestReceived(FriendRequestReceived),
+    FriendRequestAccepted(FriendRequestAccepted),
+    BlockChanged(BlockChanged),
+    UserProfileChanged(UserProfileChanged),
+    AccountRestricted(AccountRestricted),
+    DraftUpdated(DraftUpdated),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
@@ -85,11 +100,114 @@ pub(crate) struct ChatNameChanged {
     pub(crate) data: Chat,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct ChatMessageTtlChanged {
+    pub(crate) data: Chat,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
 pub(crate) struct ChatDeleted {
     pub(crate) data: Chat,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct MessageDeleted {
+    pub(crate) data: Message,
+}
+
+/// Something was attached to a message after it was sent, like a link preview.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct MessageUpdated {
+    pub(crate) data: Message,
+}
+
+/// Disappearing messages of a chat were deleted, clients should drop them from their caches.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct MessagesExpired {
+    pub(crate) chat_id: i64,
+    pub(crate) message_ids: Vec<i64>,
+    #[graphql(skip)]
+    pub(crate) member_ids: Vec<UserId>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct MessagePinned {
+    pub(crate) data: PinnedMessage,
+}
+
+/// A new message mentions me, delivered even when the chat is muted.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct Mentioned {
+    pub(crate) data: Message,
+    /// The mentioned users, every member of the chat when all are mentioned.
+    #[graphql(skip)]
+    pub(crate) user_ids: Vec<UserId>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct MessageUnpinned {
+    pub(crate) chat_id: i64,
+    pub(crate) message_id: i64,
+}
+
+/// A new reply was posted in a thread.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct ThreadUpdated {
+    pub(crate) data: MessageThread,
+    pub(crate) reply: Message,
+}
+
+/// Votes of a poll changed or it was closed.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct PollUpdated {
+    pub(crate) data: Poll,
+    #[graphql(skip)]
+    pub(crate) member_ids: Vec<UserId>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct FriendRequestReceived {
+    pub(crate) data: FriendRequest,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct FriendRequestAccepted {
+    pub(crate) data: FriendRequest,
+}
+
+/// I blocked or unblocked a user in another session.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct BlockChanged {
+    #[graphql(skip)]
+    pub(crate) user_id: UserId,
+    pub(crate) blocked_id: UserId,
+    /// `false` when the user was unblocked.
+    pub(crate) blocked: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct UserProfileChanged {
+    pub(crate) data: User,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct DraftUpdated {
+    pub(crate) chat_id: i64,
+    /// `None` when the draft is cleared.
+    pub(crate) data: Option<Draft>,
+    #[graphql(skip)]
+    pub(crate) user_id: UserId,
+    /// The session which changed the draft, it does not receive the event.
+    #[graphql(skip)]
+    pub(crate) session_id: Option<String>,
+}
+
+/// An operator suspended or banned the user, or the account was deleted. Their open sessions are closed.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub(crate) struct AccountRestricted {
+    pub(crate) user_id: UserId,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct Notification {
     pub(crate) event: AppEvent,
@@ -140,6 +258,8 @@ impl Notification {
                         AppEvent::ChatOwnerChanged(ChatOwnerChanged { data: new })
                     } else if old.name != new.name {
                         AppEvent::ChatNameChanged(ChatNameChanged { data: new })
+                    } else if old.message_ttl_seconds != new.message_ttl_seconds {
+                        AppEvent::ChatMessageTtlChanged(ChatMessageTtlChanged { data: new })
                     } else {
                         return Err(AppError::NotificationError("Invalid operation".to_string()));
                     }
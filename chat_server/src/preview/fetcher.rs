@@ -0,0 +1,323 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use async_trait::async_trait;
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::redirect::Policy;
+use url::Url;
+use crate::config::LinkPreviewConfig;
+use crate::error::AppError;
+
+/// The start of a page, at most `max_body_bytes` of it.
+#[derive(Debug, Clone)]
+pub(crate) struct FetchedPage {
+    /// The url after redirects.
+    pub(crate) url: Url,
+    pub(crate) html: String,
+}
+
+/// Loads the html of a page, replaced by a stand-in in tests.
+#[async_trait]
+pub(crate) trait PageFetcher: Send + Sync {
+    async fn fetch(&self, url: Url) -> Result<FetchedPage, AppError>;
+}
+
+/// Fetches pages over http, only from public addresses.
+/// Every redirect is checked again and the connection goes to the address which was checked.
+pub(crate) struct HttpFetcher {
+    timeout: Duration,
+    max_body_bytes: usize,
+    max_redirects: usize,
+    /// Addresses pages are fetched from, only public ones outside of tests.
+    is_allowed_ip: fn(IpAddr) -> bool,
+}
+
+impl HttpFetcher {
+    pub(crate) fn new(config: &LinkPreviewConfig) -> Self {
+        Self {
+            timeout: Duration::from_millis(config.timeout_ms),
+            max_body_bytes: config.max_body_bytes,
+            max_redirects: config.max_redirects,
+            is_allowed_ip: is_public_ip,
+        }
+    }
+}
+
+#[async_trait]
+impl PageFetcher for HttpFetcher {
+    async fn fetch(&self, url: Url) -> Result<FetchedPage, AppError> {
+        let mut url = url;
+
+        for _ in 0..=self.max_redirects {
+            let addr = resolve_allowed_addr(&url, self.is_allowed_ip).await?;
+            let host = url.host_str().unwrap_or_default().to_string();
+
+            // a proxy would connect to the host itself, not to the checked address
+            let client = reqwest::Client::builder()
+                .no_proxy()
+                .redirect(Policy::none())
+                .timeout(self.timeout)
+                .resolve(&host, addr)
+                .build()
+                .map_err(preview_error)?;
+
+            let mut response = client.get(url.clone()).send().await.map_err(preview_error)?;
+
+            if response.status().is_redirection() {
+                let location = response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|l| l.to_str().ok())
+                    .ok_or(AppError::LinkPreviewError("Redirect without location".to_string()))?;
+                url = url.join(location).map_err(preview_error)?;
+                continue;
+            }
+
+            if !response.status().is_success() {
+                return Err(AppError::LinkPreviewError(format!("Unexpected status {}", response.status())));
+            }
+
+            let is_html = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|c| c.to_str().ok())
+                .is_some_and(|c| c.to_ascii_lowercase().starts_with("text/html"));
+            if !is_html {
+                return Err(AppError::LinkPreviewError("Not an html page".to_string()));
+            }
+
+            // the metadata is in the head, so a truncated page is fine
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(preview_error)? {
+                body.extend_from_slice(&chunk);
+                if body.len() >= self.max_body_bytes {
+                    body.truncate(self.max_body_bytes);
+                    break;
+                }
+            }
+
+            return Ok(FetchedPage {
+                url,
+                html: String::from_utf8_lossy(&body).into_owned(),
+            });
+        }
+
+        Err(AppError::LinkPreviewError("Too many redirects".to_string()))
+    }
+}
+
+fn preview_error(e: impl ToString) -> AppError {
+    AppError::LinkPreviewError(e.to_string())
+}
+
+/// Resolve the host of an http(s) url, rejecting it if any of its addresses is not allowed.
+async fn resolve_allowed_addr(url: &Url, is_allowed_ip: fn(IpAddr) -> bool) -> Result<SocketAddr, AppError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(AppError::LinkPreviewError("Only http and https links are previewed".to_string()));
+    }
+
+    let host = url.host_str().ok_or(AppError::LinkPreviewError("Link without host".to_string()))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await.map_err(preview_error)?.collect();
+
+    if addrs.is_empty() || addrs.iter().any(|a| !is_allowed_ip(a.ip())) {
+        return Err(AppError::LinkPreviewError("Link does not point to a public address".to_string()));
+    }
+
+    Ok(addrs[0])
+}
+
+pub(crate) fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // this network
+        || a == 0
+        // carrier-grade nat
+        || (a == 100 && (64..128).contains(&b))
+        // ietf protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // benchmarking
+        || (a == 198 && (18..20).contains(&b))
+        // reserved
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    let first = segments[0];
+
+    // ranges which carry an ipv4 address are as public as that address
+    let embedded_ipv4 = |high: u16, low: u16| Ipv4Addr::from(((high as u32) << 16) | low as u32);
+    // ipv4-compatible, the deprecated ::a.b.c.d form
+    if segments[..6] == [0; 6] && !ip.is_loopback() && !ip.is_unspecified() {
+        return is_public_ipv4(embedded_ipv4(segments[6], segments[7]));
+    }
+    // nat64
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        return is_public_ipv4(embedded_ipv4(segments[6], segments[7]));
+    }
+    // 6to4
+    if first == 0x2002 {
+        return is_public_ipv4(embedded_ipv4(segments[1], segments[2]));
+    }
+
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // local-use nat64
+        || (first == 0x64 && segments[1] == 0xff9b && segments[2] == 0x0001)
+        // unique local
+        || (first & 0xfe00) == 0xfc00
+        // link local
+        || (first & 0xffc0) == 0xfe80
+        // site local
+        || (first & 0xffc0) == 0xfec0
+        // documentation
+        || (first == 0x2001 && segments[1] == 0x0db8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_public_ip_should_work() {
+        assert!(is_public_ip("93.184.216.34".parse().unwrap()));
+        assert!(is_public_ip("2606:2800:220:1:248:1893:25c8:1946".parse().unwrap()));
+
+        for ip in ["64:ff9b::5db8:d822", "2002:5db8:d822::1", "::5db8:d822"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{} should be public", ip);
+        }
+
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{} should not be public", ip);
+        }
+    }
+
+    #[test]
+    fn is_public_ip_should_check_embedded_ipv4() {
+        for ip in ["64:ff9b::7f00:1", "64:ff9b::a9fe:a9fe", "64:ff9b:1::1", "2002:7f00:1::1", "2002:c0a8:101::1", "::127.0.0.1", "::10.1.2.3", "fec0::1"] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{} should not be public", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_allowed_addr_should_reject_private_hosts() {
+        for url in ["http://127.0.0.1/", "http://localhost:8080/", "http://[::1]/", "ftp://example.com/"] {
+            assert!(resolve_allowed_addr(&Url::parse(url).unwrap(), is_public_ip).await.is_err(), "{} should be rejected", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn http_fetcher_should_follow_allowed_redirects() {
+        let base = serve_pages();
+
+        let page = local_fetcher(1000).fetch(base.join("/redirect").unwrap()).await.unwrap();
+
+        assert_eq!(page.url, base.join("/page").unwrap());
+        assert_eq!(page.html, "<title>Local page</title>");
+    }
+
+    #[tokio::test]
+    async fn http_fetcher_should_check_every_redirect() {
+        let base = serve_pages();
+
+        let ret = local_fetcher(1000).fetch(base.join("/redirect-private").unwrap()).await;
+
+        assert!(matches!(&ret, Err(AppError::LinkPreviewError(e)) if e.contains("public address")), "{:?}", ret);
+    }
+
+    #[tokio::test]
+    async fn http_fetcher_should_only_read_html() {
+        let base = serve_pages();
+
+        let ret = local_fetcher(1000).fetch(base.join("/image").unwrap()).await;
+
+        assert!(matches!(&ret, Err(AppError::LinkPreviewError(e)) if e.contains("html")), "{:?}", ret);
+    }
+
+    #[tokio::test]
+    async fn http_fetcher_should_cap_the_body() {
+        let base = serve_pages();
+
+        let page = local_fetcher(100).fetch(base.join("/large").unwrap()).await.unwrap();
+
+        assert_eq!(page.html.len(), 100);
+    }
+
+    /// A fetcher which only connects to 127.0.0.1, so a redirect to another loopback address is rejected.
+    fn local_fetcher(max_body_bytes: usize) -> HttpFetcher {
+        HttpFetcher {
+            timeout: Duration::from_secs(5),
+            max_body_bytes,
+            max_redirects: 3,
+            is_allowed_ip: |ip| ip == IpAddr::V4(Ipv4Addr::LOCALHOST),
+        }
+    }
+
+    /// Serve a few pages on a local port, returns the base url.
+    fn serve_pages() -> Url {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+
+                let mut request = String::new();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                reader.read_line(&mut request).unwrap();
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                        break;
+                    }
+                }
+
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let (status, headers, body) = match path {
+                    "/redirect" => ("302 Found", "Location: /page\r\n".to_string(), String::new()),
+                    "/redirect-private" => ("302 Found", format!("Location: http://127.0.0.2:{}/page\r\n", port), String::new()),
+                    "/page" => ("200 OK", "Content-Type: text/html; charset=utf-8\r\n".to_string(), "<title>Local page</title>".to_string()),
+                    "/image" => ("200 OK", "Content-Type: image/png\r\n".to_string(), "png".to_string()),
+                    "/large" => ("200 OK", "Content-Type: text/html\r\n".to_string(), "a".repeat(10_000)),
+                    _ => ("404 Not Found", String::new(), String::new()),
+                };
+
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    body.len(),
+                    body,
+                );
+            }
+        });
+
+        Url::parse(&format!("http://127.0.0.1:{}/", port)).unwrap()
+    }
+}
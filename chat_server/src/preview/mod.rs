@@ -0,0 +1,114 @@
+mod fetcher;
+mod open_graph;
+
+pub(crate) use fetcher::*;
+pub(crate) use open_graph::*;
+
+use std::sync::Arc;
+use std::time::Duration;
+use r2d2::Pool;
+use r2d2_redis::RedisConnectionManager;
+use r2d2_redis::redis::Commands;
+use tracing::debug;
+use url::Url;
+use crate::config::LinkPreviewConfig;
+use crate::error::AppError;
+use crate::models::LinkPreview;
+
+/// Builds previews of links, cached in redis by url.
+pub(crate) struct LinkPreviewService {
+    fetcher: Arc<dyn PageFetcher>,
+    rdb_pool: Pool<RedisConnectionManager>,
+    timeout: Duration,
+    cache_ttl_secs: usize,
+}
+
+impl LinkPreviewService {
+    pub(crate) fn new(fetcher: Arc<dyn PageFetcher>, rdb_pool: Pool<RedisConnectionManager>, config: &LinkPreviewConfig) -> Self {
+        Self {
+            fetcher,
+            rdb_pool,
+            timeout: Duration::from_millis(config.timeout_ms),
+            cache_ttl_secs: config.cache_ttl_secs,
+        }
+    }
+
+    /// The preview of a link, `None` when the page has no metadata or can not be fetched.
+    pub(crate) async fn preview(&self, url: &str) -> Result<Option<LinkPreview>, AppError> {
+        let key = format!("link_preview:{}", url);
+
+        {
+            let mut rdb = self.rdb_pool.get()?;
+            let cached: Option<String> = rdb.get(&key)?;
+            if let Some(cached) = cached {
+                return Ok(serde_json::from_str(&cached)?);
+            }
+        }
+
+        // failures are cached too, so a broken link is not fetched again for every message
+        let preview = match fetch_preview(self.fetcher.as_ref(), url, self.timeout).await {
+            Ok(preview) => preview,
+            Err(e) => {
+                debug!("Link preview of {} failed: {:?}", url, e);
+                None
+            }
+        };
+
+        let mut rdb = self.rdb_pool.get()?;
+        rdb.set_ex::<_, _, ()>(key, serde_json::to_string(&preview)?, self.cache_ttl_secs)?;
+
+        Ok(preview)
+    }
+}
+
+pub(crate) async fn fetch_preview(fetcher: &dyn PageFetcher, url: &str, timeout: Duration) -> Result<Option<LinkPreview>, AppError> {
+    let url = Url::parse(url).map_err(|e| AppError::LinkPreviewError(e.to_string()))?;
+
+    let page = tokio::time::timeout(timeout, fetcher.fetch(url))
+        .await
+        .map_err(|_| AppError::LinkPreviewError("Timed out".to_string()))??;
+
+    Ok(parse_preview(&page.html, &page.url))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use super::*;
+
+    /// Serves fixed pages instead of going to the network.
+    struct StubFetcher {
+        pages: Vec<(&'static str, &'static str)>,
+    }
+
+    #[async_trait]
+    impl PageFetcher for StubFetcher {
+        async fn fetch(&self, url: Url) -> Result<FetchedPage, AppError> {
+            let html = self.pages
+                .iter()
+                .find(|(page_url, _)| *page_url == url.as_str())
+                .map(|(_, html)| html.to_string())
+                .ok_or(AppError::LinkPreviewError("Not found".to_string()))?;
+
+            Ok(FetchedPage { url, html })
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_preview_should_work() {
+        let fetcher = StubFetcher {
+            pages: vec![
+                ("https://example.com/", r#"<meta property="og:title" content="Example">"#),
+                ("https://example.com/empty", "<p>nothing</p>"),
+            ],
+        };
+        let timeout = Duration::from_secs(1);
+
+        let preview = fetch_preview(&fetcher, "https://example.com/", timeout).await.unwrap().unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Example"));
+
+        assert_eq!(fetch_preview(&fetcher, "https://example.com/empty", timeout).await.unwrap(), None);
+        assert!(fetch_preview(&fetcher, "https://example.com/missing", timeout).await.is_err());
+        assert!(fetch_preview(&fetcher, "not a url", timeout).await.is_err());
+    }
+}
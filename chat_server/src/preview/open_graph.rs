@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use url::Url;
+use crate::models::LinkPreview;
+
+const MAX_TITLE_CHARS: usize = 300;
+const MAX_DESCRIPTION_CHARS: usize = 1000;
+const MAX_SITE_NAME_CHARS: usize = 100;
+
+/// Build a preview from the OpenGraph tags of a page, falling back to `<title>` and the description meta tag.
+/// Returns `None` when the page has neither a title nor a description.
+pub(crate) fn parse_preview(html: &str, url: &Url) -> Option<LinkPreview> {
+    // ascii lowercase keeps byte offsets, so positions found in it are valid in `html`
+    let lower = html.to_ascii_lowercase();
+    let mut meta: HashMap<String, String> = HashMap::new();
+
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<meta").map(|i| i + pos) {
+        let Some(end) = lower[start..].find('>').map(|i| i + start) else {
+            break;
+        };
+
+        let attrs = parse_attributes(&html[start + "<meta".len()..end]);
+        let key = attrs.get("property").or(attrs.get("name"));
+        if let (Some(key), Some(content)) = (key, attrs.get("content")) {
+            meta.entry(key.to_ascii_lowercase()).or_insert_with(|| decode_entities(content));
+        }
+
+        pos = end;
+    }
+
+    let title = meta.remove("og:title").or_else(|| {
+        let start = lower.find("<title")?;
+        let start = lower[start..].find('>')? + start + 1;
+        let end = lower[start..].find("</title")? + start;
+        Some(decode_entities(&html[start..end]))
+    });
+    let description = meta.remove("og:description").or(meta.remove("description"));
+
+    let title = title.map(|t| truncate(t.trim(), MAX_TITLE_CHARS)).filter(|t| !t.is_empty());
+    let description = description.map(|d| truncate(d.trim(), MAX_DESCRIPTION_CHARS)).filter(|d| !d.is_empty());
+
+    if title.is_none() && description.is_none() {
+        return None;
+    }
+
+    let image_url = meta
+        .remove("og:image")
+        .and_then(|image| url.join(image.trim()).ok())
+        .filter(|image| matches!(image.scheme(), "http" | "https"))
+        .map(String::from);
+
+    Some(LinkPreview {
+        url: url.to_string(),
+        title,
+        description,
+        image_url,
+        site_name: meta.remove("og:site_name").map(|s| truncate(s.trim(), MAX_SITE_NAME_CHARS)).filter(|s| !s.is_empty()),
+    })
+}
+
+/// Attributes of a tag, names lowercased.
+fn parse_attributes(tag: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut chars = tag.char_indices().peekable();
+
+    loop {
+        while chars.next_if(|(_, c)| c.is_whitespace() || *c == '/').is_some() {}
+
+        let Some(&(name_start, _)) = chars.peek() else {
+            break;
+        };
+        let mut name_end = tag.len();
+        while let Some(&(i, c)) = chars.peek() {
+            if c.is_whitespace() || c == '=' || c == '/' {
+                name_end = i;
+                break;
+            }
+            chars.next();
+        }
+        let name = tag[name_start..name_end].to_ascii_lowercase();
+
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        if chars.next_if(|(_, c)| *c == '=').is_none() {
+            attrs.entry(name).or_insert_with(String::new);
+            continue;
+        }
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+
+        let value = match chars.peek() {
+            Some(&(i, quote)) if quote == '"' || quote == '\'' => {
+                chars.next();
+                let end = tag[i + 1..].find(quote).map_or(tag.len(), |e| e + i + 1);
+                while chars.next_if(|(j, _)| *j <= end).is_some() {}
+                &tag[i + 1..end]
+            }
+            Some(&(i, _)) => {
+                let end = tag[i..].find(char::is_whitespace).map_or(tag.len(), |e| e + i);
+                while chars.next_if(|(j, _)| *j < end).is_some() {}
+                &tag[i..end]
+            }
+            None => "",
+        };
+
+        attrs.entry(name).or_insert_with(|| value.to_string());
+    }
+
+    attrs
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_preview_should_read_open_graph() {
+        let html = r#"
+            <html><head>
+            <title>Fallback</title>
+            <META property="og:title" content="Tom &amp; Jerry">
+            <meta content='A cat and a mouse' property=og:description />
+            <meta property="og:image" content="/img/cover.png">
+            <meta property="og:site_name" content="Cartoons">
+            </head></html>
+        "#;
+        let url = Url::parse("https://example.com/shows/1").unwrap();
+
+        let preview = parse_preview(html, &url).unwrap();
+
+        assert_eq!(preview.url, "https://example.com/shows/1");
+        assert_eq!(preview.title.as_deref(), Some("Tom & Jerry"));
+        assert_eq!(preview.description.as_deref(), Some("A cat and a mouse"));
+        assert_eq!(preview.image_url.as_deref(), Some("https://example.com/img/cover.png"));
+        assert_eq!(preview.site_name.as_deref(), Some("Cartoons"));
+    }
+
+    #[test]
+    fn parse_preview_should_fall_back_to_title_and_description() {
+        let html = r#"<title> Plain page </title><meta name="description" content="Just a page">"#;
+        let url = Url::parse("https://example.com/").unwrap();
+
+        let preview = parse_preview(html, &url).unwrap();
+
+        assert_eq!(preview.title.as_deref(), Some("Plain page"));
+        assert_eq!(preview.description.as_deref(), Some("Just a page"));
+        assert_eq!(preview.image_url, None);
+
+        assert_eq!(parse_preview("<html><body>nothing</body></html>", &url), None);
+    }
+
+    #[test]
+    fn parse_preview_should_truncate_long_texts() {
+        let html = format!(
+            r#"<meta property="og:title" content="{}"><meta property="og:site_name" content="{}">"#,
+            "t".repeat(400),
+            "s".repeat(200),
+        );
+        let url = Url::parse("https://example.com/").unwrap();
+
+        let preview = parse_preview(&html, &url).unwrap();
+
+        assert_eq!(preview.title.unwrap().chars().count(), MAX_TITLE_CHARS + 1);
+        assert_eq!(preview.site_name.unwrap(), format!("{}…", "s".repeat(MAX_SITE_NAME_CHARS)));
+    }
+}
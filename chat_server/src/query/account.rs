@@ -0,0 +1,17 @@
+use async_graphql::{Context, Object};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::models::{DataExport, UserId};
+
+#[derive(Default)]
+pub(crate) struct AccountQuery;
+
+#[Object]
+impl AccountQuery {
+    async fn my_data_exports(&self, ctx: &Context<'_>) -> Result<Vec<DataExport>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.account_repo.get_exports(*user_id).await
+    }
+}
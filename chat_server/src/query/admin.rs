@@ -0,0 +1,111 @@
+use async_graphql::{Context, Object};
+use async_graphql::connection::{Connection, Edge};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::guard::RoleGuard;
+use crate::models::{page_size, AdminChat, AdminUser, Message, Report, ReportStatus, SystemStats, UserId, UserRole};
+
+#[derive(Default)]
+pub(crate) struct AdminQuery;
+
+#[Object]
+impl AdminQuery {
+    /// `after` is the id of the last user of the previous page.
+    #[graphql(guard = "RoleGuard::new(UserRole::Moderator)")]
+    async fn admin_users(
+        &self,
+        ctx: &Context<'_>,
+        query: Option<String>,
+        role: Option<UserRole>,
+        first: Option<i32>,
+        after: Option<UserId>,
+    ) -> Result<Connection<UserId, AdminUser>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+
+        let query = query.as_deref().map(str::trim).filter(|q| !q.is_empty());
+
+        let limit = page_size(first, &state.config.message);
+        let mut users = state.admin_repo.list_users(query, role, after, limit + 1).await?;
+
+        let has_next_page = users.len() as i64 > limit;
+        users.truncate(limit as usize);
+
+        let mut connection = Connection::new(after.is_some(), has_next_page);
+        connection.edges.extend(
+            users.into_iter().map(|u| Edge::new(u.id, u))
+        );
+
+        Ok(connection)
+    }
+
+    #[graphql(guard = "RoleGuard::new(UserRole::Moderator)")]
+    async fn admin_user(&self, ctx: &Context<'_>, user_id: UserId) -> Result<AdminUser, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+
+        state.admin_repo.get_user(user_id).await
+    }
+
+    #[graphql(guard = "RoleGuard::new(UserRole::Moderator)")]
+    async fn admin_chat(&self, ctx: &Context<'_>, chat_id: i64) -> Result<AdminChat, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+
+        state.admin_repo.get_chat(chat_id).await
+    }
+
+    /// Messages of any chat, newest first. `before` is the id of the last message of the previous page.
+    #[graphql(guard = "RoleGuard::new(UserRole::Moderator)")]
+    async fn admin_chat_messages(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        first: Option<i32>,
+        before: Option<i64>,
+    ) -> Result<Connection<i64, Message>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+
+        let limit = page_size(first, &state.config.message);
+        let mut messages = state.admin_repo.get_chat_messages(chat_id, before, limit + 1).await?;
+
+        let has_next_page = messages.len() as i64 > limit;
+        messages.truncate(limit as usize);
+
+        let mut connection = Connection::new(before.is_some(), has_next_page);
+        connection.edges.extend(
+            messages.into_iter().map(|m| Edge::new(m.id, m))
+        );
+
+        Ok(connection)
+    }
+
+    /// `after` is the id of the last report of the previous page.
+    #[graphql(guard = "RoleGuard::new(UserRole::Moderator)")]
+    async fn admin_reports(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<ReportStatus>,
+        first: Option<i32>,
+        after: Option<i64>,
+    ) -> Result<Connection<i64, Report>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+
+        let limit = page_size(first, &state.config.message);
+        let mut reports = state.admin_repo.list_reports(status, after, limit + 1).await?;
+
+        let has_next_page = reports.len() as i64 > limit;
+        reports.truncate(limit as usize);
+
+        let mut connection = Connection::new(after.is_some(), has_next_page);
+        connection.edges.extend(
+            reports.into_iter().map(|r| Edge::new(r.id, r))
+        );
+
+        Ok(connection)
+    }
+
+    #[graphql(guard = "RoleGuard::new(UserRole::Admin)")]
+    async fn system_stats(&self, ctx: &Context<'_>) -> Result<SystemStats, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+
+        state.admin_repo.get_system_stats().await
+    }
+}
@@ -1,8 +1,9 @@
 use async_graphql::{Context, ErrorExtensions, Object};
+use async_graphql::connection::{Connection, CursorType, Edge};
 
 use crate::app_state::AppState;
 use crate::error::AppError;
-use crate::models::{Chat, UserId};
+use crate::models::{page_size, Chat, ChatCursor, UserId};
 
 #[derive(Default)]
 pub(crate) struct ChatQuery;
@@ -25,17 +26,32 @@ impl ChatQuery {
         }
     }
 
-    async fn get_chats(&self, ctx: &Context<'_>) -> Result<Vec<Chat>, AppError> {
+    /// Chats of the current user, pinned first and then by last activity.
+    async fn get_chats(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        include_archived: Option<bool>,
+    ) -> Result<Connection<ChatCursor, Chat>, AppError> {
         let state = ctx.data_unchecked::<AppState>();
         let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
 
-        println!("user_id: {:?}", user_id);
+        let after = after.map(|c| ChatCursor::decode_cursor(&c)).transpose()?;
+        let limit = page_size(first, &state.config.message);
 
-        let res = state.chat_repo.get_all_chats(*user_id).await;
+        let mut chats = state.chat_repo
+            .get_chats_page(*user_id, include_archived.unwrap_or(false), after, limit + 1)
+            .await?;
 
-        match res {
-            Ok(chats) => Ok(chats),
-            Err(_) => Ok(vec![])
-        }
+        let has_next_page = chats.len() as i64 > limit;
+        chats.truncate(limit as usize);
+
+        let mut connection = Connection::new(after.is_some(), has_next_page);
+        connection.edges.extend(
+            chats.into_iter().map(|(cursor, chat)| Edge::new(cursor, chat))
+        );
+
+        Ok(connection)
     }
 }
@@ -0,0 +1,32 @@
+use async_graphql::{Context, Object};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::models::{FriendRequest, User, UserId};
+
+#[derive(Default)]
+pub(crate) struct ContactQuery;
+
+#[Object]
+impl ContactQuery {
+    async fn my_contacts(&self, ctx: &Context<'_>) -> Result<Vec<User>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.contact_repo.get_contacts(*user_id).await
+    }
+
+    async fn my_blocked_users(&self, ctx: &Context<'_>) -> Result<Vec<User>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.contact_repo.get_blocked_users(*user_id).await
+    }
+
+    /// Friend requests sent to me and waiting for my answer.
+    async fn pending_requests(&self, ctx: &Context<'_>) -> Result<Vec<FriendRequest>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.contact_repo.get_pending_requests(*user_id).await
+    }
+}
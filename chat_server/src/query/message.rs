@@ -1,26 +1,170 @@
 use async_graphql::{Context, ErrorExtensions, InputObject, Object};
+use async_graphql::connection::{Connection, Edge};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use jwt_simple::prelude::{Deserialize, Serialize};
 use crate::app_state::AppState;
 use crate::error::AppError;
-use crate::models::{Message, MessageType, UserId};
+use crate::models::{page_size, Message, MessageSearchHit, MessageType, ScheduledMessage, ScheduledMessageStatus, StarredMessage, UserId};
+use crate::repository::{MessagePosition, MessageSearchFilter};
 
 #[derive(Default)]
 pub(crate) struct MessageQuery;
 
 #[Object]
 impl MessageQuery {
+    /// Messages of a chat sorted by id. At most one of `before`, `after` and `around` can be given,
+    /// otherwise the latest messages are returned. `around` includes the message itself.
+    /// Thread replies are left out unless `include_thread_replies` is set.
     async fn get_messages(
         &self,
         ctx: &Context<'_>,
         chat_id: i64,
-        cursor_id: Option<i64>,
-    ) -> Result<Vec<Message>, AppError> {
+        before: Option<i64>,
+        after: Option<i64>,
+        around: Option<i64>,
+        first: Option<i32>,
+        #[graphql(default)] include_thread_replies: bool,
+    ) -> Result<Connection<i64, Message>, AppError> {
         let state = ctx.data_unchecked::<AppState>();
         let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
 
-        let messages = state.message_repo.get_messages(chat_id, *user_id, cursor_id).await?;
+        let position = match (before, after, around) {
+            (None, None, None) => MessagePosition::Latest,
+            (Some(id), None, None) => MessagePosition::Before(id),
+            (None, Some(id), None) => MessagePosition::After(id),
+            (None, None, Some(id)) => MessagePosition::Around(id),
+            _ => return Err(AppError::InvalidInput("Only one of before, after and around can be given".to_string())),
+        };
 
-        Ok(messages)
+        let limit = page_size(first, &state.config.message);
+
+        let page = state.message_repo
+            .get_messages(chat_id, *user_id, position, limit, include_thread_replies)
+            .await?;
+
+        let mut connection = Connection::new(page.has_more_before, page.has_more_after);
+        connection.edges.extend(
+            page.messages.into_iter().map(|m| Edge::new(m.id, m))
+        );
+
+        Ok(connection)
+    }
+
+    /// Replies in the thread of a message, oldest first. `after` is the id of the last reply of the previous page.
+    async fn thread_replies(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: i64,
+        root_id: i64,
+        first: Option<i32>,
+        after: Option<i64>,
+    ) -> Result<Connection<i64, Message>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let limit = page_size(first, &state.config.message);
+
+        let mut replies = state.message_repo
+            .get_thread_replies(chat_id, *user_id, root_id, after, limit + 1)
+            .await?;
+
+        let has_next_page = replies.len() as i64 > limit;
+        replies.truncate(limit as usize);
+
+        let mut connection = Connection::new(after.is_some(), has_next_page);
+        connection.edges.extend(
+            replies.into_iter().map(|m| Edge::new(m.id, m))
+        );
+
+        Ok(connection)
+    }
+
+    /// Search messages in my chats, newest first. Only text messages are searched unless `type` is given.
+    /// `after` is the id of the last hit of the previous page.
+    async fn search_messages(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        chat_id: Option<i64>,
+        sender_id: Option<UserId>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        r#type: Option<MessageType>,
+        first: Option<i32>,
+        after: Option<i64>,
+    ) -> Result<Connection<i64, MessageSearchHit>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let query = query.trim();
+        if query.is_empty() {
+            return Err(AppError::InvalidInput("Search query can not be empty".to_string()));
+        }
+
+        let filter = MessageSearchFilter {
+            chat_id,
+            sender_id,
+            from,
+            to,
+            r#type,
+        };
+
+        let limit = page_size(first, &state.config.message);
+
+        let mut hits = state.message_repo
+            .search_messages(*user_id, query, &filter, after, limit + 1)
+            .await?;
+
+        let has_next_page = hits.len() as i64 > limit;
+        hits.truncate(limit as usize);
+
+        let mut connection = Connection::new(after.is_some(), has_next_page);
+        connection.edges.extend(
+            hits.into_iter().map(|h| Edge::new(h.message.id, h))
+        );
+
+        Ok(connection)
+    }
+
+    /// Messages I starred in all my chats, latest starred first.
+    /// `after` is the `starId` of the last message of the previous page.
+    async fn my_starred_messages(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<i64>,
+    ) -> Result<Connection<i64, StarredMessage>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let limit = page_size(first, &state.config.message);
+
+        let mut starred = state.message_repo.get_starred_messages(*user_id, after, limit + 1).await?;
+
+        let has_next_page = starred.len() as i64 > limit;
+        starred.truncate(limit as usize);
+
+        let mut connection = Connection::new(after.is_some(), has_next_page);
+        connection.edges.extend(
+            starred.into_iter().map(|s| Edge::new(s.star_id, s))
+        );
+
+        Ok(connection)
+    }
+
+    /// My scheduled messages sorted by send time, the pending ones unless `status` is given.
+    async fn my_scheduled_messages(
+        &self,
+        ctx: &Context<'_>,
+        chat_id: Option<i64>,
+        status: Option<ScheduledMessageStatus>,
+    ) -> Result<Vec<ScheduledMessage>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.schedule_repo
+            .get_scheduled_messages(*user_id, chat_id, status.unwrap_or(ScheduledMessageStatus::Pending))
+            .await
     }
 }
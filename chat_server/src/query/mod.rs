@@ -2,6 +2,10 @@ mod chat;
 mod user;
 mod message;
 mod file;
+mod contact;
+mod account;
+mod admin;
+mod sticker;
 
 use async_graphql::{MergedObject};
 
@@ -9,6 +13,10 @@ pub(crate) use chat::*;
 pub(crate) use user::*;
 pub(crate) use message::*;
 pub(crate) use file::*;
+pub(crate) use contact::*;
+pub(crate) use account::*;
+pub(crate) use admin::*;
+pub(crate) use sticker::*;
 
 #[derive(MergedObject, Default)]
-pub(crate) struct QueryRoot(UserQuery, ChatQuery, MessageQuery, FileQuery);
+pub(crate) struct QueryRoot(UserQuery, ChatQuery, MessageQuery, FileQuery, ContactQuery, AccountQuery, AdminQuery, StickerQuery);
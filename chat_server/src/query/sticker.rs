@@ -0,0 +1,26 @@
+use async_graphql::{Context, Object};
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::models::{StickerPack, UserId};
+
+#[derive(Default)]
+pub(crate) struct StickerQuery;
+
+#[Object]
+impl StickerQuery {
+    /// My custom stickers, the server-managed packs and the packs I added.
+    async fn sticker_packs(&self, ctx: &Context<'_>) -> Result<Vec<StickerPack>, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.sticker_repo.get_sticker_packs(*user_id).await
+    }
+
+    /// A pack I can see, e.g. of a sticker sent in one of my chats.
+    async fn sticker_pack(&self, ctx: &Context<'_>, pack_id: i64) -> Result<StickerPack, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.sticker_repo.get_sticker_pack(*user_id, pack_id).await?.ok_or(AppError::StickerNotFound)
+    }
+}
@@ -1,25 +1,47 @@
 use async_graphql::{Context, Object};
+use async_graphql::connection::{Connection, Edge};
 use anyhow::Result;
 use crate::app_state::AppState;
 use crate::error::{AppError};
-use crate::models::{User, UserId};
+use crate::models::{page_size, PrivacySettings, User, UserId};
 
 #[derive(Default)]
 pub(crate) struct UserQuery;
 
 #[Object]
 impl UserQuery {
-    async fn get_users(
+    /// Find users by fullname prefix, or by email prefix if they are discoverable by email.
+    /// Users with a block between them and me are not found.
+    /// `after` is the id of the last user of the previous page.
+    async fn search_users(
         &self,
         ctx: &Context<'_>,
-    ) ->Result<Vec<User>, AppError> {
+        query: String,
+        first: Option<i32>,
+        after: Option<UserId>,
+    ) -> Result<Connection<UserId, User>, AppError> {
         let state = ctx.data_unchecked::<AppState>();
-        let _user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        let query = query.trim();
+        if query.is_empty() {
+            return Err(AppError::InvalidInput("Search query can not be empty".to_string()));
+        }
 
-        let users = state.user_repo.get_all_users().await?;
+        let limit = page_size(first, &state.config.message);
+        let mut users = state.user_repo.search_users(*user_id, query, after, limit + 1).await?;
 
-        Ok(users)
+        let has_next_page = users.len() as i64 > limit;
+        users.truncate(limit as usize);
+
+        let mut connection = Connection::new(after.is_some(), has_next_page);
+        connection.edges.extend(
+            users.into_iter().map(|u| Edge::new(u.id, u))
+        );
+
+        Ok(connection)
     }
+
     async fn get_self(&self, ctx: &Context<'_>) -> Result<User, AppError> {
         let state = ctx.data_unchecked::<AppState>();
         let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
@@ -31,4 +53,11 @@ impl UserQuery {
             Some(u) => return Ok(u)
         }
     }
+
+    async fn get_privacy_settings(&self, ctx: &Context<'_>) -> Result<PrivacySettings, AppError> {
+        let state = ctx.data_unchecked::<AppState>();
+        let user_id = ctx.data::<UserId>().map_err(|_| AppError::GetGraphqlUserIdError)?;
+
+        state.user_repo.get_privacy_settings(*user_id).await
+    }
 }
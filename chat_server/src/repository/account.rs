@@ -0,0 +1,374 @@
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::info;
+use crate::error::AppError;
+use crate::models::{Chat, DataExport, DataExportStatus, Message, NewSystemEvent, SystemAction, User, UserId};
+use crate::repository::insert_system_message;
+
+pub struct AccountRepository {
+    pub(crate) pool: PgPool,
+}
+
+impl AccountRepository {
+    pub(crate) fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+        }
+    }
+
+    /// Create a pending export, or return the one which is still pending.
+    /// Returns whether the export was created, only a created one has to be built.
+    pub(crate) async fn create_export(&self, user_id: UserId) -> Result<(DataExport, bool), AppError> {
+        loop {
+            let created: Option<DataExport> = sqlx::query_as(
+                r#"
+                INSERT INTO data_exports (user_id)
+                VALUES ($1)
+                ON CONFLICT (user_id) WHERE status = 'pending' DO NOTHING
+                RETURNING id, user_id, status, created_at, finished_at, expires_at
+                "#,
+            )
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+            if let Some(export) = created {
+                return Ok((export, true));
+            }
+
+            let pending: Option<DataExport> = sqlx::query_as(
+                r#"
+                SELECT id, user_id, status, created_at, finished_at, expires_at
+                FROM data_exports
+                WHERE user_id = $1 AND status = 'pending'
+                "#,
+            )
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+            // the pending export may have finished in between, then a new one is created
+            if let Some(pending) = pending {
+                return Ok((pending, false));
+            }
+        }
+    }
+
+    pub(crate) async fn get_exports(&self, user_id: UserId) -> Result<Vec<DataExport>, AppError> {
+        let exports: Vec<DataExport> = sqlx::query_as(
+            r#"
+            SELECT id, user_id, status, created_at, finished_at, expires_at
+            FROM data_exports
+            WHERE user_id = $1
+            ORDER BY id DESC
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(exports)
+    }
+
+    /// Content of a finished export which has not expired yet.
+    pub(crate) async fn get_export_content(&self, export_id: i64, user_id: UserId) -> Result<Option<serde_json::Value>, AppError> {
+        let content: Option<(serde_json::Value,)> = sqlx::query_as(
+            r#"
+            SELECT content
+            FROM data_exports
+            WHERE id = $1 AND user_id = $2 AND status = 'ready' AND expires_at > now()
+            "#,
+        )
+            .bind(export_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(content.map(|(c,)| c))
+    }
+
+    /// Collect the profile, chats and sent messages of the user into the export.
+    pub(crate) async fn build_export(&self, export_id: i64, user_id: UserId, ttl_hours: i64) -> Result<(), AppError> {
+        let profile: User = sqlx::query_as(
+            r#"
+            SELECT id, fullname, email, avatar, created_at, email_visibility, avatar_visibility, bio, status_text
+            FROM users
+            WHERE id = $1
+            "#,
+        )
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let chats: Vec<Chat> = sqlx::query_as(
+            r#"
+            SELECT c.id, c.name, c.owner_id, c.type, c.created_at, c.last_activity_at, c.block_links, c.message_ttl_seconds
+            FROM chats c
+            JOIN chat_members cm ON c.id = cm.chat_id
+            WHERE cm.user_id = $1
+            ORDER BY c.id
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let messages: Vec<Message> = sqlx::query_as(
+            r#"
+            SELECT id, chat_id, user_id, type, content, created_at, thread_root_id, expires_at
+            FROM messages
+            WHERE user_id = $1
+            ORDER BY id
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let content = json!({
+            "exportedAt": Utc::now(),
+            "profile": profile,
+            "chats": chats,
+            "messages": messages,
+        });
+
+        sqlx::query(
+            r#"
+            UPDATE data_exports
+            SET status = $2, content = $3, finished_at = now(), expires_at = now() + make_interval(hours => $4::INT)
+            WHERE id = $1
+            "#,
+        )
+            .bind(export_id)
+            .bind(DataExportStatus::Ready)
+            .bind(content)
+            .bind(ttl_hours as i32)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn mark_export_failed(&self, export_id: i64) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE data_exports
+            SET status = $2, finished_at = now()
+            WHERE id = $1
+            "#,
+        )
+            .bind(export_id)
+            .bind(DataExportStatus::Failed)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn schedule_deletion(&self, user_id: UserId, grace_days: i64) -> Result<DateTime<Utc>, AppError> {
+        let scheduled_at = Utc::now() + Duration::days(grace_days);
+
+        let ret = sqlx::query(
+            r#"
+            UPDATE users
+            SET deletion_scheduled_at = $2
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+        )
+            .bind(user_id)
+            .bind(scheduled_at)
+            .execute(&self.pool)
+            .await?;
+
+        if ret.rows_affected() == 0 {
+            return Err(AppError::UserNotFound);
+        }
+
+        Ok(scheduled_at)
+    }
+
+    pub(crate) async fn cancel_deletion(&self, user_id: UserId) -> Result<bool, AppError> {
+        let ret = sqlx::query(
+            r#"
+            UPDATE users
+            SET deletion_scheduled_at = NULL
+            WHERE id = $1 AND deleted_at IS NULL AND deletion_scheduled_at IS NOT NULL
+            "#,
+        )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(ret.rows_affected() == 1)
+    }
+
+    pub(crate) async fn get_due_deletions(&self) -> Result<Vec<UserId>, AppError> {
+        let ids: Vec<(UserId,)> = sqlx::query_as(
+            r#"
+            SELECT id
+            FROM users
+            WHERE deleted_at IS NULL AND deletion_scheduled_at <= now()
+            "#,
+        )
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(ids.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// Anonymize the user instead of deleting the row, so the history of other members stays intact.
+    /// Owned groups are handed over to the member who joined first, or dissolved if nobody is left.
+    /// The user leaves every group but stays in private chats, which show up as a deleted user.
+    /// Pending scheduled messages are cancelled and custom stickers deleted, sticker messages keep their text.
+    /// The remaining members see system messages about the new owner and the leave.
+    pub(crate) async fn anonymize_user(&self, user_id: UserId) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let user: Option<(UserId,)> = sqlx::query_as(
+            r#"
+            SELECT id
+            FROM users
+            WHERE id = $1 AND deleted_at IS NULL
+            FOR UPDATE
+            "#,
+        )
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        if user.is_none() {
+            return Ok(());
+        }
+
+        let owned_groups: Vec<(i64,)> = sqlx::query_as(
+            r#"
+            SELECT id
+            FROM chats
+            WHERE owner_id = $1 AND type = 'group'
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let mut new_owners = Vec::new();
+        for (chat_id,) in owned_groups {
+            let next_owner: Option<(UserId,)> = sqlx::query_as(
+                r#"
+                SELECT user_id
+                FROM chat_members
+                WHERE chat_id = $1 AND user_id != $2
+                ORDER BY created_at, user_id
+                LIMIT 1
+                "#,
+            )
+                .bind(chat_id)
+                .bind(user_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+            match next_owner {
+                Some((next_owner,)) => {
+                    sqlx::query("UPDATE chats SET owner_id = $2 WHERE id = $1")
+                        .bind(chat_id)
+                        .bind(next_owner)
+                        .execute(&mut *tx)
+                        .await?;
+
+                    new_owners.push((chat_id, next_owner));
+                }
+                None => {
+                    sqlx::query("DELETE FROM chat_members WHERE chat_id = $1")
+                        .bind(chat_id)
+                        .execute(&mut *tx)
+                        .await?;
+
+                    let sql = format!("ALTER TABLE messages DETACH PARTITION zzz_messages_chat_{};", chat_id);
+                    sqlx::query(&sql).execute(&mut *tx).await?;
+
+                    let sql = format!("DROP TABLE zzz_messages_chat_{};", chat_id);
+                    sqlx::query(&sql).execute(&mut *tx).await?;
+
+                    sqlx::query("DELETE FROM chats WHERE id = $1")
+                        .bind(chat_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+            }
+        }
+
+        let left_groups: Vec<(i64,)> = sqlx::query_as(
+            r#"
+            DELETE FROM chat_members
+            WHERE user_id = $1 AND chat_id IN (SELECT id FROM chats WHERE type = 'group')
+            RETURNING chat_id
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        for sql in [
+            "DELETE FROM contacts WHERE user_id = $1 OR contact_id = $1",
+            "DELETE FROM friend_requests WHERE from_user_id = $1 OR to_user_id = $1",
+            "DELETE FROM user_blocks WHERE user_id = $1",
+            "DELETE FROM data_exports WHERE user_id = $1",
+            "UPDATE scheduled_messages SET status = 'cancelled' WHERE user_id = $1 AND status = 'pending'",
+            "DELETE FROM sticker_packs WHERE owner_id = $1",
+            "UPDATE message_forwards SET origin_user_id = NULL, origin_sender_name = 'Deleted user' WHERE origin_user_id = $1",
+            "UPDATE message_contacts SET user_id = NULL, display_name = 'Deleted user' WHERE user_id = $1",
+        ] {
+            sqlx::query(sql)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET fullname = 'Deleted user',
+                email = 'deleted-' || id || '@deleted.invalid',
+                password_hash = '',
+                avatar = NULL,
+                bio = NULL,
+                status_text = NULL,
+                email_discoverable = FALSE,
+                email_visibility = 'nobody',
+                avatar_visibility = 'nobody',
+                deletion_scheduled_at = NULL,
+                deleted_at = now()
+            WHERE id = $1
+            "#,
+        )
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // after the anonymization, so the messages do not keep the name of the account
+        for (chat_id, next_owner) in new_owners {
+            insert_system_message(&mut tx, chat_id, NewSystemEvent {
+                actor_id: user_id,
+                action: SystemAction::OwnerChanged,
+                target_ids: vec![next_owner],
+                detail: None,
+            }).await?;
+        }
+
+        for (chat_id,) in left_groups {
+            insert_system_message(&mut tx, chat_id, NewSystemEvent {
+                actor_id: user_id,
+                action: SystemAction::MemberLeft,
+                target_ids: vec![],
+                detail: None,
+            }).await?;
+        }
+
+        tx.commit().await?;
+
+        info!("Account {} anonymized", user_id);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use crate::error::AppError;
+use crate::models::{AdminChat, AdminUser, Message, Report, ReportStatus, SystemStats, UserId, UserRole};
+use crate::repository::delete_messages;
+use crate::utils::escape_like;
+
+pub struct AdminRepository {
+    pub(crate) pool: PgPool,
+}
+
+impl AdminRepository {
+    pub(crate) fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+        }
+    }
+
+    /// List users sorted by id, optionally filtered by fullname or email prefix and role.
+    pub(crate) async fn list_users(
+        &self,
+        query: Option<&str>,
+        role: Option<UserRole>,
+        after_id: Option<UserId>,
+        limit: i64,
+    ) -> Result<Vec<AdminUser>, AppError> {
+        let users: Vec<AdminUser> = sqlx::query_as(
+            r#"
+            SELECT id, fullname, email, avatar, role, created_at, suspended_until, suspension_reason, banned, deletion_scheduled_at, deleted_at
+            FROM users
+            WHERE ($1::TEXT IS NULL OR lower(fullname) LIKE lower($1) || '%' OR lower(email) LIKE lower($1) || '%')
+            AND ($2::user_role IS NULL OR role = $2)
+            AND id > $3
+            ORDER BY id
+            LIMIT $4
+            "#,
+        )
+            .bind(query.map(escape_like))
+            .bind(role)
+            .bind(after_id.unwrap_or_default())
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(users)
+    }
+
+    pub(crate) async fn get_user(&self, user_id: UserId) -> Result<AdminUser, AppError> {
+        let user: Option<AdminUser> = sqlx::query_as(
+            r#"
+            SELECT id, fullname, email, avatar, role, created_at, suspended_until, suspension_reason, banned, deletion_scheduled_at, deleted_at
+            FROM users
+            WHERE id = $1
+            "#,
+        )
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+
+    /// Suspend the user until the given time, or indefinitely when it is `None`.
+    /// An indefinite suspension ends in year 9999, as `infinity` can not be read back into a `DateTime`.
+    pub(crate) async fn suspend_user(
+        &self,
+        user_id: UserId,
+        until: Option<DateTime<Utc>>,
+        reason: &str,
+    ) -> Result<AdminUser, AppError> {
+        let user: Option<AdminUser> = sqlx::query_as(
+            r#"
+            UPDATE users
+            SET suspended_until = COALESCE($2, '9999-12-31 23:59:59+00'::TIMESTAMPTZ), suspension_reason = $3
+            WHERE id = $1
+            RETURNING id, fullname, email, avatar, role, created_at, suspended_until, suspension_reason, banned, deletion_scheduled_at, deleted_at
+            "#,
+        )
+            .bind(user_id)
+            .bind(until)
+            .bind(reason)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+
+    pub(crate) async fn unsuspend_user(&self, user_id: UserId) -> Result<AdminUser, AppError> {
+        let user: Option<AdminUser> = sqlx::query_as(
+            r#"
+            UPDATE users
+            SET suspended_until = NULL, suspension_reason = CASE WHEN banned THEN suspension_reason END
+            WHERE id = $1
+            RETURNING id, fullname, email, avatar, role, created_at, suspended_until, suspension_reason, banned, deletion_scheduled_at, deleted_at
+            "#,
+        )
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+
+    pub(crate) async fn ban_user(&self, user_id: UserId, reason: &str) -> Result<AdminUser, AppError> {
+        let user: Option<AdminUser> = sqlx::query_as(
+            r#"
+            UPDATE users
+            SET banned = TRUE, suspension_reason = $2
+            WHERE id = $1
+            RETURNING id, fullname, email, avatar, role, created_at, suspended_until, suspension_reason, banned, deletion_scheduled_at, deleted_at
+            "#,
+        )
+            .bind(user_id)
+            .bind(reason)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+
+    pub(crate) async fn unban_user(&self, user_id: UserId) -> Result<AdminUser, AppError> {
+        let user: Option<AdminUser> = sqlx::query_as(
+            r#"
+            UPDATE users
+            SET banned = FALSE, suspension_reason = CASE WHEN suspended_until > now() THEN suspension_reason END
+            WHERE id = $1
+            RETURNING id, fullname, email, avatar, role, created_at, suspended_until, suspension_reason, banned, deletion_scheduled_at, deleted_at
+            "#,
+        )
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+
+    pub(crate) async fn set_role(&self, user_id: UserId, role: UserRole) -> Result<AdminUser, AppError> {
+        let user: Option<AdminUser> = sqlx::query_as(
+            r#"
+            UPDATE users
+            SET role = $2
+            WHERE id = $1
+            RETURNING id, fullname, email, avatar, role, created_at, suspended_until, suspension_reason, banned, deletion_scheduled_at, deleted_at
+            "#,
+        )
+            .bind(user_id)
+            .bind(role)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+
+    /// Get any chat, no matter whether the operator is a member.
+    pub(crate) async fn get_chat(&self, chat_id: i64) -> Result<AdminChat, AppError> {
+        let chat: Option<AdminChat> = sqlx::query_as(
+            r#"
+            SELECT c.id, c.name, c.owner_id, c.type, c.created_at, c.last_activity_at, c.block_links, c.message_ttl_seconds,
+                COALESCE(
+                    (SELECT array_agg(cm.user_id ORDER BY cm.created_at, cm.user_id) FROM chat_members cm WHERE cm.chat_id = c.id),
+                    '{}'
+                ) AS member_ids
+            FROM chats c
+            WHERE c.id = $1
+            "#,
+        )
+            .bind(chat_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        chat.ok_or(AppError::ChatNotFound)
+    }
+
+    /// Messages of a chat, newest first, older than `before_id` if given.
+    pub(crate) async fn get_chat_messages(
+        &self,
+        chat_id: i64,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<Message>, AppError> {
+        let messages: Vec<Message> = sqlx::query_as(
+            r#"
+            SELECT id, chat_id, user_id, type, content, created_at, thread_root_id, expires_at
+            FROM messages
+            WHERE chat_id = $1 AND ($2::BIGINT IS NULL OR id < $2)
+            ORDER BY id DESC
+            LIMIT $3
+            "#,
+        )
+            .bind(chat_id)
+            .bind(before_id)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(messages)
+    }
+
+    /// Delete a message with everything attached to it, and the replies of its thread when it is a thread root.
+    /// Returns the deleted messages, the requested one first.
+    pub(crate) async fn delete_message(&self, chat_id: i64, message_id: i64) -> Result<Vec<Message>, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let messages = delete_messages(&mut tx, &[(chat_id, message_id)]).await?;
+
+        if messages.is_empty() {
+            return Err(AppError::InvalidInput("Message not found".to_string()));
+        }
+
+        tx.commit().await?;
+
+        Ok(messages)
+    }
+
+    /// Reports sorted by id, optionally filtered by status.
+    pub(crate) async fn list_reports(
+        &self,
+        status: Option<ReportStatus>,
+        after_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<Report>, AppError> {
+        let reports: Vec<Report> = sqlx::query_as(
+            r#"
+            SELECT id, reporter_id, target_user_id, chat_id, message_id, reason, description, context, status, created_at, handled_by, handled_at
+            FROM reports
+            WHERE ($1::report_status IS NULL OR status = $1)
+            AND id > $2
+            ORDER BY id
+            LIMIT $3
+            "#,
+        )
+            .bind(status)
+            .bind(after_id.unwrap_or_default())
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(reports)
+    }
+
+    pub(crate) async fn handle_report(
+        &self,
+        report_id: i64,
+        moderator_id: UserId,
+        status: ReportStatus,
+    ) -> Result<Report, AppError> {
+        if status == ReportStatus::Open {
+            return Err(AppError::InvalidInput("A report can only be resolved or dismissed".to_string()));
+        }
+
+        let report: Option<Report> = sqlx::query_as(
+            r#"
+            UPDATE reports
+            SET status = $3, handled_by = $2, handled_at = now()
+            WHERE id = $1
+            RETURNING id, reporter_id, target_user_id, chat_id, message_id, reason, description, context, status, created_at, handled_by, handled_at
+            "#,
+        )
+            .bind(report_id)
+            .bind(moderator_id)
+            .bind(status)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        report.ok_or(AppError::InvalidInput("Report not found".to_string()))
+    }
+
+    pub(crate) async fn get_system_stats(&self) -> Result<SystemStats, AppError> {
+        let stats: SystemStats = sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT count(*) FROM users WHERE deleted_at IS NULL) AS user_count,
+                (SELECT count(*) FROM users WHERE created_at > now() - INTERVAL '24 hours') AS new_user_count,
+                (SELECT count(*) FROM users WHERE banned OR suspended_until > now()) AS suspended_user_count,
+                (SELECT count(*) FROM chats) AS chat_count,
+                (SELECT count(*) FROM chats WHERE type = 'group') AS group_chat_count,
+                (SELECT count(*) FROM messages) AS message_count,
+                (SELECT count(*) FROM messages WHERE created_at > now() - INTERVAL '24 hours') AS new_message_count,
+                (SELECT count(*) FROM reports WHERE status = 'open') AS open_report_count
+            "#,
+        )
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(stats)
+    }
+}
@@ -1,19 +1,37 @@
 use log::debug;
 use crate::error::AppError;
-use sqlx::{PgPool};
+use sqlx::{FromRow, PgPool};
 use tracing::field::debug;
-use crate::models::{Chat, ChatType, Message, User, UserId};
+use chrono::{DateTime, Utc};
+use crate::models::{Chat, ChatCursor, ChatMemberSettings, ChatNickname, ChatType, Draft, Message, NewSystemEvent, SystemAction, User, UserId};
+use crate::repository::insert_system_message;
+
+#[derive(Debug, FromRow)]
+struct ChatListRow {
+    #[sqlx(flatten)]
+    chat: Chat,
+    sort_pinned_order: i32,
+}
+
+#[derive(Debug, FromRow)]
+struct ChatMemberRow {
+    chat_id: i64,
+    #[sqlx(flatten)]
+    user: User,
+}
 
 pub struct ChatRepository {
     biz: String,
     pub(crate) pool: PgPool,
+    require_contact_for_private_chat: bool,
 }
 
 impl ChatRepository {
-    pub(crate) fn new(pool: PgPool) -> Self {
+    pub(crate) fn new(pool: PgPool, require_contact_for_private_chat: bool) -> Self {
         Self {
             biz: "chat".to_string(),
             pool,
+            require_contact_for_private_chat,
         }
     }
 
@@ -22,7 +40,7 @@ impl ChatRepository {
 
         let chat: Chat = sqlx::query_as(
             r#"
-            SELECT id, name, type, owner_id, created_at
+            SELECT id, name, type, owner_id, created_at, last_activity_at, block_links, message_ttl_seconds
             FROM chats
             WHERE id = $1
             "#,
@@ -100,7 +118,8 @@ impl ChatRepository {
         let ret = sqlx::query(
             r#"
             UPDATE chat_members
-            SET unread_count = $3
+            SET unread_count = $3,
+                unread_mention_count = CASE WHEN $3 = 0 THEN 0 ELSE unread_mention_count END
             WHERE chat_id = $1 AND user_id = $2
             "#,
         )
@@ -113,42 +132,90 @@ impl ChatRepository {
         Ok(ret.rows_affected() == 1)
     }
 
-    pub(crate) async fn get_unread_count(&self, chat_id: i64, user_id: UserId) -> Result<i32, AppError> {
-        let count: (i32,) = sqlx::query_as(
+    pub(crate) async fn get_members(&self, chat_id: i64) -> Result<Vec<User>, AppError> {
+        let users: Vec<User> = sqlx::query_as(
             r#"
-            SELECT unread_count
-            FROM chat_members
-            WHERE chat_id = $1 AND user_id = $2
+            SELECT u.id, u.fullname, u.email, u.avatar, u.created_at, u.email_visibility, u.avatar_visibility, u.bio, u.status_text
+            FROM users u
+            JOIN chat_members cm ON u.id = cm.user_id
+            WHERE cm.chat_id = $1
             "#,
         )
             .bind(chat_id)
-            .bind(user_id)
-            .fetch_one(&self.pool)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(users)
+    }
+
+    pub(crate) async fn get_unread_counts(&self, keys: &[(i64, UserId)]) -> Result<Vec<(i64, UserId, i32)>, AppError> {
+        let (chat_ids, user_ids): (Vec<i64>, Vec<UserId>) = keys.iter().cloned().unzip();
+
+        let counts: Vec<(i64, UserId, i32)> = sqlx::query_as(
+            r#"
+            SELECT cm.chat_id, cm.user_id, COALESCE(cm.unread_count, 0)
+            FROM chat_members cm
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, user_id)
+            ON cm.chat_id = k.chat_id AND cm.user_id = k.user_id
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(user_ids)
+            .fetch_all(&self.pool)
             .await?;
 
-        Ok(count.0)
+        Ok(counts)
     }
 
-    pub(crate) async fn get_members(&self, chat_id: i64) -> Result<Vec<User>, AppError> {
-        let users: Vec<User> = sqlx::query_as(
+    pub(crate) async fn get_unread_mention_counts(&self, keys: &[(i64, UserId)]) -> Result<Vec<(i64, UserId, i32)>, AppError> {
+        let (chat_ids, user_ids): (Vec<i64>, Vec<UserId>) = keys.iter().cloned().unzip();
+
+        let counts: Vec<(i64, UserId, i32)> = sqlx::query_as(
             r#"
-            SELECT u.id, u.fullname, u.email, u.avatar, u.created_at
+            SELECT cm.chat_id, cm.user_id, cm.unread_mention_count
+            FROM chat_members cm
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, user_id)
+            ON cm.chat_id = k.chat_id AND cm.user_id = k.user_id
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(user_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(counts)
+    }
+
+    pub(crate) async fn get_member_ids(&self, chat_id: i64) -> Result<Vec<UserId>, AppError> {
+        let ids: Vec<(UserId,)> = sqlx::query_as("SELECT user_id FROM chat_members WHERE chat_id = $1")
+            .bind(chat_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(ids.into_iter().map(|(id,)| id).collect())
+    }
+
+    pub(crate) async fn get_members_by_chat_ids(&self, chat_ids: &[i64]) -> Result<Vec<(i64, User)>, AppError> {
+        let rows: Vec<ChatMemberRow> = sqlx::query_as(
+            r#"
+            SELECT cm.chat_id, u.id, u.fullname, u.email, u.avatar, u.created_at, u.email_visibility, u.avatar_visibility, u.bio, u.status_text
             FROM users u
             JOIN chat_members cm ON u.id = cm.user_id
-            WHERE cm.chat_id = $1
+            WHERE cm.chat_id = ANY($1)
+            ORDER BY cm.chat_id, cm.created_at, u.id
             "#,
         )
-            .bind(chat_id)
+            .bind(chat_ids)
             .fetch_all(&self.pool)
             .await?;
 
-        Ok(users)
+        Ok(rows.into_iter().map(|row| (row.chat_id, row.user)).collect())
     }
 
     pub(crate) async fn get_chat_by_id(&self, id: i64, user_id: UserId) -> Result<Chat, AppError> {
         let chat: Chat = sqlx::query_as(
             r#"
-            SELECT c.id, c.name, c.owner_id, c.type, c.created_at
+            SELECT c.id, c.name, c.owner_id, c.type, c.created_at, c.last_activity_at, c.block_links, c.message_ttl_seconds
             FROM chats c
             JOIN chat_members cm ON c.id = cm.chat_id
             WHERE c.id = $1 AND cm.user_id = $2
@@ -162,37 +229,260 @@ impl ChatRepository {
         Ok(chat)
     }
 
-    pub(crate) async fn get_all_chats(&self, user_id: UserId) -> Result<Vec<Chat>, AppError> {
-        let chats: Vec<Chat> = sqlx::query_as(
+    /// Page through the chats of a user, pinned chats first, then by last activity.
+    /// Returns at most `limit` chats after the cursor.
+    pub(crate) async fn get_chats_page(
+        &self,
+        user_id: UserId,
+        include_archived: bool,
+        after: Option<ChatCursor>,
+        limit: i64,
+    ) -> Result<Vec<(ChatCursor, Chat)>, AppError> {
+        let rows: Vec<ChatListRow> = sqlx::query_as(
             r#"
-            SELECT c.id, c.name, c.owner_id, c.type, c.created_at
+            SELECT c.id, c.name, c.owner_id, c.type, c.created_at, c.last_activity_at, c.block_links, c.message_ttl_seconds,
+                COALESCE(cm.pinned_order, 0) AS sort_pinned_order
             FROM chats c
             JOIN chat_members cm ON c.id = cm.chat_id
-            WHERE cm.user_id = $1
+            WHERE cm.user_id = $1 AND ($2 OR cm.archived = FALSE)
+            AND ($3::BOOLEAN IS FALSE OR (COALESCE(cm.pinned_order, 0), c.last_activity_at, c.id) < ($4, $5, $6))
+            ORDER BY COALESCE(cm.pinned_order, 0) DESC, c.last_activity_at DESC, c.id DESC
+            LIMIT $7
             "#,
         )
             .bind(user_id)
+            .bind(include_archived)
+            .bind(after.is_some())
+            .bind(after.map(|c| c.pinned_order).unwrap_or_default())
+            .bind(after.map(|c| c.last_activity_at).unwrap_or_default())
+            .bind(after.map(|c| c.id).unwrap_or_default())
+            .bind(limit)
             .fetch_all(&self.pool)
             .await?;
 
+        let chats = rows
+            .into_iter()
+            .map(|row| {
+                let cursor = ChatCursor {
+                    pinned_order: row.sort_pinned_order,
+                    last_activity_at: row.chat.last_activity_at,
+                    id: row.chat.id,
+                };
+                (cursor, row.chat)
+            })
+            .collect();
+
         Ok(chats)
     }
 
-    pub(crate) async fn get_latest_message(&self, chat_id: i64) -> Result<Option<Message>, AppError> {
-        let message: Option<Message> = sqlx::query_as(
+    pub(crate) async fn get_member_settings(&self, chat_id: i64, user_id: UserId) -> Result<ChatMemberSettings, AppError> {
+        let settings: Option<ChatMemberSettings> = sqlx::query_as(
             r#"
-            SELECT id, chat_id, user_id, type, content, created_at
-            FROM messages
-            WHERE chat_id = $1
-            ORDER BY created_at DESC
-            LIMIT 1
+            SELECT chat_id, user_id, muted_until, pinned_order, archived, nickname
+            FROM chat_members
+            WHERE chat_id = $1 AND user_id = $2
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        settings.ok_or(AppError::ChatNotFound)
+    }
+
+    pub(crate) async fn get_nicknames(&self, chat_id: i64) -> Result<Vec<ChatNickname>, AppError> {
+        let nicknames: Vec<ChatNickname> = sqlx::query_as(
+            r#"
+            SELECT user_id, nickname
+            FROM chat_members
+            WHERE chat_id = $1 AND nickname IS NOT NULL
+            "#,
+        )
+            .bind(chat_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(nicknames)
+    }
+
+    pub(crate) async fn set_muted_until(
+        &self,
+        chat_id: i64,
+        user_id: UserId,
+        muted_until: Option<DateTime<Utc>>,
+    ) -> Result<ChatMemberSettings, AppError> {
+        let settings: Option<ChatMemberSettings> = sqlx::query_as(
+            r#"
+            UPDATE chat_members
+            SET muted_until = $3
+            WHERE chat_id = $1 AND user_id = $2
+            RETURNING chat_id, user_id, muted_until, pinned_order, archived, nickname
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .bind(muted_until)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        settings.ok_or(AppError::ChatNotFound)
+    }
+
+    pub(crate) async fn set_pinned(&self, chat_id: i64, user_id: UserId, pinned: bool) -> Result<ChatMemberSettings, AppError> {
+        // a newly pinned chat goes above every chat the user has already pinned
+        let settings: Option<ChatMemberSettings> = sqlx::query_as(
+            r#"
+            UPDATE chat_members
+            SET pinned_order = CASE WHEN $3 THEN (
+                SELECT COALESCE(MAX(pinned_order), 0) + 1
+                FROM chat_members
+                WHERE user_id = $2
+            ) ELSE NULL END
+            WHERE chat_id = $1 AND user_id = $2
+            RETURNING chat_id, user_id, muted_until, pinned_order, archived, nickname
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .bind(pinned)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        settings.ok_or(AppError::ChatNotFound)
+    }
+
+    pub(crate) async fn set_archived(&self, chat_id: i64, user_id: UserId, archived: bool) -> Result<ChatMemberSettings, AppError> {
+        let settings: Option<ChatMemberSettings> = sqlx::query_as(
+            r#"
+            UPDATE chat_members
+            SET archived = $3
+            WHERE chat_id = $1 AND user_id = $2
+            RETURNING chat_id, user_id, muted_until, pinned_order, archived, nickname
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .bind(archived)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        settings.ok_or(AppError::ChatNotFound)
+    }
+
+    pub(crate) async fn set_nickname(
+        &self,
+        chat_id: i64,
+        user_id: UserId,
+        nickname: Option<String>,
+    ) -> Result<ChatMemberSettings, AppError> {
+        let nickname = nickname
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty());
+
+        if let Some(nickname) = &nickname {
+            if nickname.chars().count() > 64 {
+                return Err(AppError::ChatError("Nickname is too long".to_string()));
+            }
+        }
+
+        let chat = self.get_chat_by_id(chat_id, user_id).await.map_err(|_| AppError::ChatNotFound)?;
+        if chat.r#type != ChatType::Group {
+            return Err(AppError::ChatError("Nickname is only available in group chat".to_string()));
+        }
+
+        let settings: ChatMemberSettings = sqlx::query_as(
+            r#"
+            UPDATE chat_members
+            SET nickname = $3
+            WHERE chat_id = $1 AND user_id = $2
+            RETURNING chat_id, user_id, muted_until, pinned_order, archived, nickname
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .bind(nickname)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(settings)
+    }
+
+    /// Save the draft of a member, an empty content clears it.
+    pub(crate) async fn save_draft(&self, chat_id: i64, user_id: UserId, content: &str) -> Result<Option<Draft>, AppError> {
+        if content.trim().is_empty() {
+            self.clear_draft(chat_id, user_id).await?;
+            return Ok(None);
+        }
+
+        let draft: Option<Draft> = sqlx::query_as(
+            r#"
+            UPDATE chat_members
+            SET draft = $3, draft_updated_at = now()
+            WHERE chat_id = $1 AND user_id = $2
+            RETURNING chat_id, user_id, draft AS content, draft_updated_at AS updated_at
             "#,
         )
             .bind(chat_id)
+            .bind(user_id)
+            .bind(content)
             .fetch_optional(&self.pool)
             .await?;
 
-        Ok(message)
+        draft.ok_or(AppError::ChatNotFound).map(Some)
+    }
+
+    /// Returns whether there was a draft to clear.
+    pub(crate) async fn clear_draft(&self, chat_id: i64, user_id: UserId) -> Result<bool, AppError> {
+        let ret = sqlx::query(
+            r#"
+            UPDATE chat_members
+            SET draft = NULL, draft_updated_at = now()
+            WHERE chat_id = $1 AND user_id = $2 AND draft IS NOT NULL
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(ret.rows_affected() == 1)
+    }
+
+    pub(crate) async fn get_drafts(&self, keys: &[(i64, UserId)]) -> Result<Vec<Draft>, AppError> {
+        let (chat_ids, user_ids): (Vec<i64>, Vec<UserId>) = keys.iter().cloned().unzip();
+
+        let drafts: Vec<Draft> = sqlx::query_as(
+            r#"
+            SELECT cm.chat_id, cm.user_id, cm.draft AS content, cm.draft_updated_at AS updated_at
+            FROM chat_members cm
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, user_id)
+            ON cm.chat_id = k.chat_id AND cm.user_id = k.user_id
+            WHERE cm.draft IS NOT NULL
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(user_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(drafts)
+    }
+
+    pub(crate) async fn get_latest_messages(&self, chat_ids: &[i64]) -> Result<Vec<Message>, AppError> {
+        let messages: Vec<Message> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT ON (chat_id) id, chat_id, user_id, type, content, created_at, thread_root_id, expires_at
+            FROM messages
+            WHERE chat_id = ANY($1) AND thread_root_id IS NULL
+            ORDER BY chat_id, id DESC
+            "#,
+        )
+            .bind(chat_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(messages)
     }
 
     pub(crate) async fn update_chat_name(
@@ -200,16 +490,56 @@ impl ChatRepository {
         name: String,
         chat_id: i64,
         owner_id: UserId,
+    ) -> Result<bool, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let ret: Option<(ChatType, String)> = sqlx::query_as(
+            r#"
+            UPDATE chats c
+            SET name = $1
+            FROM (SELECT id, name FROM chats WHERE id = $2 FOR UPDATE) old
+            WHERE c.id = old.id AND c.owner_id = $3
+            RETURNING c.type, old.name
+            "#
+        )
+            .bind(&name)
+            .bind(chat_id)
+            .bind(owner_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let Some((chat_type, old_name)) = ret else {
+            return Ok(false);
+        };
+
+        if chat_type == ChatType::Group && old_name != name {
+            insert_system_message(&mut tx, chat_id, NewSystemEvent {
+                actor_id: owner_id,
+                action: SystemAction::GroupRenamed,
+                target_ids: vec![],
+                detail: Some(name),
+            }).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    pub(crate) async fn set_block_links(
+        &self,
+        chat_id: i64,
+        owner_id: UserId,
+        block_links: bool,
     ) -> Result<bool, AppError> {
         let ret = sqlx::query(
             r#"
             UPDATE chats
-            SET name = $1
+            SET block_links = $1
             WHERE id = $2 AND owner_id = $3
-            RETURNING id
             "#
         )
-            .bind(name)
+            .bind(block_links)
             .bind(chat_id)
             .bind(owner_id)
             .execute(&self.pool)
@@ -218,6 +548,33 @@ impl ChatRepository {
         Ok(ret.rows_affected() == 1)
     }
 
+    /// The owner of a group chat, or either member of a private chat, can set the timer.
+    pub(crate) async fn set_message_ttl(
+        &self,
+        chat_id: i64,
+        user_id: UserId,
+        ttl_seconds: Option<i32>,
+    ) -> Result<Chat, AppError> {
+        let chat: Option<Chat> = sqlx::query_as(
+            r#"
+            UPDATE chats c
+            SET message_ttl_seconds = $1
+            WHERE c.id = $2 AND (
+                c.owner_id = $3
+                OR (c.type = 'private' AND EXISTS (SELECT 1 FROM chat_members cm WHERE cm.chat_id = c.id AND cm.user_id = $3))
+            )
+            RETURNING c.id, c.name, c.owner_id, c.type, c.created_at, c.last_activity_at, c.block_links, c.message_ttl_seconds
+            "#
+        )
+            .bind(ttl_seconds)
+            .bind(chat_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        chat.ok_or(AppError::ChatNotFound)
+    }
+
     pub(crate) async fn create(
         &self,
         owner_id: UserId,
@@ -246,7 +603,7 @@ impl ChatRepository {
             let query_member_ids: Vec<_> = member_ids.iter().take(3).collect();
             let ret: Result<Vec<User>, _> = sqlx::query_as(
                 r#"
-            SELECT id, fullname, email, avatar, created_at
+            SELECT id, fullname, email, avatar, created_at, email_visibility, avatar_visibility, bio, status_text
             FROM users
             WHERE id = ANY($1)
             "#,
@@ -276,9 +633,26 @@ impl ChatRepository {
                 }
             }
 
+            let blocked: Option<(UserId,)> = sqlx::query_as(
+                r#"
+                SELECT blocked_id
+                FROM user_blocks
+                WHERE user_id = $1 AND blocked_id = $2
+                "#,
+            )
+                .bind(another_user_id)
+                .bind(owner_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+            if blocked.is_some() {
+                tx.rollback().await?;
+                return Err(AppError::CreateChatError("Can not start a private chat with this user".to_string()));
+            }
+
             let ret: Result<Chat, _> = sqlx::query_as(
                 r#"
-                SELECT c.id, c.owner_id, c."type", c.name, c.created_at
+                SELECT c.id, c.owner_id, c."type", c.name, c.created_at, c.last_activity_at, c.block_links, c.message_ttl_seconds
                 FROM chats c
                 JOIN chat_members cm
                 ON cm.chat_id = c.id
@@ -296,6 +670,25 @@ impl ChatRepository {
                 tx.commit().await?;
                 return Ok(chat);
             }
+
+            if self.require_contact_for_private_chat {
+                let contact: Option<(UserId,)> = sqlx::query_as(
+                    r#"
+                    SELECT contact_id
+                    FROM contacts
+                    WHERE user_id = $1 AND contact_id = $2
+                    "#,
+                )
+                    .bind(owner_id)
+                    .bind(another_user_id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+
+                if contact.is_none() {
+                    tx.rollback().await?;
+                    return Err(AppError::CreateChatError("Can only start a private chat with contacts".to_string()));
+                }
+            }
         }
 
 
@@ -303,7 +696,7 @@ impl ChatRepository {
             r#"
             INSERT INTO chats (owner_id, type, name, created_at)
             VALUES ($1, $2, $3, now())
-            RETURNING id, owner_id, type, name, created_at
+            RETURNING id, owner_id, type, name, created_at, last_activity_at, block_links, message_ttl_seconds
             "#,
         )
         .bind(owner_id)
@@ -314,7 +707,7 @@ impl ChatRepository {
 
         match ret {
             Ok(chat) => {
-                for member_id in member_ids {
+                for member_id in &member_ids {
                     let ret = sqlx::query(
                         r#"
                 INSERT INTO chat_members (chat_id, user_id, created_at)
@@ -351,6 +744,15 @@ impl ChatRepository {
                     }
                 }
 
+                if chat.r#type == ChatType::Group {
+                    insert_system_message(&mut tx, chat.id, NewSystemEvent {
+                        actor_id: owner_id,
+                        action: SystemAction::GroupCreated,
+                        target_ids: member_ids.into_iter().filter(|id| *id != owner_id).collect(),
+                        detail: Some(chat.name.clone()),
+                    }).await?;
+                }
+
                 tx.commit().await?;
 
                 Ok(chat)
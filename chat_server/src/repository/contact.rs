@@ -0,0 +1,286 @@
+use sqlx::PgPool;
+use crate::error::AppError;
+use crate::models::{FriendRequest, FriendRequestStatus, User, UserId};
+
+pub struct ContactRepository {
+    pub(crate) pool: PgPool,
+}
+
+impl ContactRepository {
+    pub(crate) fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+        }
+    }
+
+    pub(crate) async fn is_contact(&self, user_id: UserId, contact_id: UserId) -> Result<bool, AppError> {
+        let ret: Option<(UserId,)> = sqlx::query_as(
+            r#"
+            SELECT contact_id
+            FROM contacts
+            WHERE user_id = $1 AND contact_id = $2
+            "#,
+        )
+            .bind(user_id)
+            .bind(contact_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(ret.is_some())
+    }
+
+    pub(crate) async fn get_contacts(&self, user_id: UserId) -> Result<Vec<User>, AppError> {
+        let users: Vec<User> = sqlx::query_as(
+            r#"
+            SELECT u.id, u.fullname, u.email, u.avatar, u.created_at, u.email_visibility, u.avatar_visibility, u.bio, u.status_text
+            FROM users u
+            JOIN contacts c ON u.id = c.contact_id
+            WHERE c.user_id = $1
+            ORDER BY u.fullname, u.id
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(users)
+    }
+
+    /// Friend requests sent to the user which are not handled yet.
+    pub(crate) async fn get_pending_requests(&self, user_id: UserId) -> Result<Vec<FriendRequest>, AppError> {
+        let requests: Vec<FriendRequest> = sqlx::query_as(
+            r#"
+            SELECT id, from_user_id, to_user_id, message, status, created_at, updated_at
+            FROM friend_requests
+            WHERE to_user_id = $1 AND status = 'pending'
+            ORDER BY id DESC
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(requests)
+    }
+
+    /// Send a friend request. If the other user already sent one to me, it is accepted instead.
+    pub(crate) async fn send_request(
+        &self,
+        from_user_id: UserId,
+        to_user_id: UserId,
+        message: Option<String>,
+    ) -> Result<FriendRequest, AppError> {
+        if from_user_id == to_user_id {
+            return Err(AppError::ContactError("Can not add yourself as a contact".to_string()));
+        }
+
+        if self.is_contact(from_user_id, to_user_id).await? {
+            return Err(AppError::ContactError("Already in contacts".to_string()));
+        }
+
+        let blocked: Option<(UserId,)> = sqlx::query_as(
+            r#"
+            SELECT blocked_id
+            FROM user_blocks
+            WHERE user_id = $1 AND blocked_id = $2
+            "#,
+        )
+            .bind(to_user_id)
+            .bind(from_user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        if blocked.is_some() {
+            return Err(AppError::ContactError("Can not send a friend request to this user".to_string()));
+        }
+
+        let reverse: Option<FriendRequest> = sqlx::query_as(
+            r#"
+            SELECT id, from_user_id, to_user_id, message, status, created_at, updated_at
+            FROM friend_requests
+            WHERE from_user_id = $1 AND to_user_id = $2 AND status = 'pending'
+            "#,
+        )
+            .bind(to_user_id)
+            .bind(from_user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        if let Some(reverse) = reverse {
+            return self.accept_request(reverse.id, from_user_id).await;
+        }
+
+        // sending the same request again keeps the pending one
+        let request: FriendRequest = sqlx::query_as(
+            r#"
+            INSERT INTO friend_requests (from_user_id, to_user_id, message)
+            SELECT $1, id, $3 FROM users WHERE id = $2
+            ON CONFLICT (from_user_id, to_user_id) WHERE status = 'pending'
+            DO UPDATE SET message = EXCLUDED.message, updated_at = now()
+            RETURNING id, from_user_id, to_user_id, message, status, created_at, updated_at
+            "#,
+        )
+            .bind(from_user_id)
+            .bind(to_user_id)
+            .bind(message)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        Ok(request)
+    }
+
+    pub(crate) async fn accept_request(&self, request_id: i64, user_id: UserId) -> Result<FriendRequest, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let request: FriendRequest = sqlx::query_as(
+            r#"
+            UPDATE friend_requests
+            SET status = $3, updated_at = now()
+            WHERE id = $1 AND to_user_id = $2 AND status = 'pending'
+            RETURNING id, from_user_id, to_user_id, message, status, created_at, updated_at
+            "#,
+        )
+            .bind(request_id)
+            .bind(user_id)
+            .bind(FriendRequestStatus::Accepted)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(AppError::FriendRequestNotFound)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO contacts (user_id, contact_id)
+            VALUES ($1, $2), ($2, $1)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+            .bind(request.from_user_id)
+            .bind(request.to_user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(request)
+    }
+
+    pub(crate) async fn reject_request(&self, request_id: i64, user_id: UserId) -> Result<FriendRequest, AppError> {
+        let request: FriendRequest = sqlx::query_as(
+            r#"
+            UPDATE friend_requests
+            SET status = $3, updated_at = now()
+            WHERE id = $1 AND to_user_id = $2 AND status = 'pending'
+            RETURNING id, from_user_id, to_user_id, message, status, created_at, updated_at
+            "#,
+        )
+            .bind(request_id)
+            .bind(user_id)
+            .bind(FriendRequestStatus::Rejected)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(AppError::FriendRequestNotFound)?;
+
+        Ok(request)
+    }
+
+    pub(crate) async fn block_user(&self, user_id: UserId, blocked_id: UserId) -> Result<bool, AppError> {
+        if user_id == blocked_id {
+            return Err(AppError::ContactError("Can not block yourself".to_string()));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let ret = sqlx::query(
+            r#"
+            INSERT INTO user_blocks (user_id, blocked_id)
+            SELECT $1, id FROM users WHERE id = $2
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+            .bind(user_id)
+            .bind(blocked_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // pending requests between the two are dropped, in both directions
+        sqlx::query(
+            r#"
+            DELETE FROM friend_requests
+            WHERE status = 'pending'
+            AND ((from_user_id = $1 AND to_user_id = $2) OR (from_user_id = $2 AND to_user_id = $1))
+            "#,
+        )
+            .bind(user_id)
+            .bind(blocked_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(ret.rows_affected() == 1)
+    }
+
+    pub(crate) async fn unblock_user(&self, user_id: UserId, blocked_id: UserId) -> Result<bool, AppError> {
+        let ret = sqlx::query(
+            r#"
+            DELETE FROM user_blocks
+            WHERE user_id = $1 AND blocked_id = $2
+            "#,
+        )
+            .bind(user_id)
+            .bind(blocked_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(ret.rows_affected() == 1)
+    }
+
+    pub(crate) async fn get_blocked_users(&self, user_id: UserId) -> Result<Vec<User>, AppError> {
+        let users: Vec<User> = sqlx::query_as(
+            r#"
+            SELECT u.id, u.fullname, u.email, u.avatar, u.created_at, u.email_visibility, u.avatar_visibility, u.bio, u.status_text
+            FROM users u
+            JOIN user_blocks b ON u.id = b.blocked_id
+            WHERE b.user_id = $1
+            ORDER BY b.created_at DESC
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(users)
+    }
+
+    /// Ids of the users `user_id` has blocked.
+    pub(crate) async fn get_blocked_ids(&self, user_id: UserId) -> Result<Vec<UserId>, AppError> {
+        let ids: Vec<(UserId,)> = sqlx::query_as(
+            r#"
+            SELECT blocked_id
+            FROM user_blocks
+            WHERE user_id = $1
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(ids.into_iter().map(|(id,)| id).collect())
+    }
+
+    pub(crate) async fn remove_contact(&self, user_id: UserId, contact_id: UserId) -> Result<bool, AppError> {
+        let ret = sqlx::query(
+            r#"
+            DELETE FROM contacts
+            WHERE (user_id = $1 AND contact_id = $2) OR (user_id = $2 AND contact_id = $1)
+            "#,
+        )
+            .bind(user_id)
+            .bind(contact_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(ret.rows_affected() > 0)
+    }
+}
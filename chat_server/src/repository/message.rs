@@ -1,6 +1,44 @@
-use sqlx::PgPool;
+use std::collections::BTreeMap;
+use sqlx::{PgConnection, PgPool};
 use crate::error::AppError;
-use crate::models::{Chat, Message, MessageType, UserId};
+use chrono::{DateTime, Utc};
+use crate::filter::OutgoingMessage;
+use crate::models::{Chat, ChatType, ContactPayload, ForwardOrigin, ForwardSource, LinkPreview, LocationPayload, Message, MessageLinkPreview, MessageMentions, MessageSearchHit, MessageThread, MessageType, NewMessagePayload, NewSystemEvent, PinnedMessage, StarredMessage, SystemAction, SystemPayload, UserId};
+use crate::utils::{escape_like, highlight_snippet, render_headline, HEADLINE_START, HEADLINE_STOP};
+
+const MAX_PINNED_MESSAGES: i64 = 50;
+
+/// Where a page of messages should be loaded from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MessagePosition {
+    Latest,
+    Before(i64),
+    After(i64),
+    Around(i64),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MessagePage {
+    pub(crate) messages: Vec<Message>,
+    pub(crate) has_more_before: bool,
+    pub(crate) has_more_after: bool,
+}
+
+/// A copy of `source` about to be saved by `forward_messages`, after the message filters ran on it.
+#[derive(Debug, Clone)]
+pub(crate) struct ForwardCopy {
+    pub(crate) message: OutgoingMessage,
+    pub(crate) source: ForwardSource,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MessageSearchFilter {
+    pub(crate) chat_id: Option<i64>,
+    pub(crate) sender_id: Option<UserId>,
+    pub(crate) from: Option<DateTime<Utc>>,
+    pub(crate) to: Option<DateTime<Utc>>,
+    pub(crate) r#type: Option<MessageType>,
+}
 
 pub struct MessageRepository {
     biz: String,
@@ -15,61 +53,1180 @@ impl MessageRepository {
         }
     }
 
-    pub(crate) async fn get_messages(&self, chat_id: i64, user_id: UserId, cursor_id: Option<i64>) -> Result<Vec<Message>, AppError> {
-        let mut messages: Vec<Message> = sqlx::query_as(
+    /// Load a page of messages in a chat, always sorted by id ascending.
+    pub(crate) async fn get_messages(
+        &self,
+        chat_id: i64,
+        user_id: UserId,
+        position: MessagePosition,
+        limit: i64,
+        include_thread_replies: bool,
+    ) -> Result<MessagePage, AppError> {
+        let is_member: Option<(i64,)> = sqlx::query_as(
+            r#"
+            SELECT chat_id
+            FROM chat_members
+            WHERE chat_id = $1 AND user_id = $2
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        if is_member.is_none() {
+            return Err(AppError::ChatNotFound);
+        }
+
+        let (older, newer) = match position {
+            MessagePosition::Latest => (self.fetch_older(chat_id, i64::MAX, limit, include_thread_replies).await?, vec![]),
+            MessagePosition::Before(id) => (self.fetch_older(chat_id, id, limit, include_thread_replies).await?, vec![]),
+            MessagePosition::After(id) => (vec![], self.fetch_newer(chat_id, id, limit, include_thread_replies).await?),
+            MessagePosition::Around(id) => {
+                // the target message itself is the first one of the newer half
+                let half = limit / 2;
+                let older = self.fetch_older(chat_id, id, half, include_thread_replies).await?;
+                let newer = self.fetch_newer(chat_id, id.saturating_sub(1), limit - half, include_thread_replies).await?;
+                (older, newer)
+            }
+        };
+
+        let first_id = older.last().or(newer.first()).map(|m| m.id);
+        let last_id = newer.last().or(older.first()).map(|m| m.id);
+
+        let mut messages: Vec<Message> = older.into_iter().rev().collect();
+        messages.extend(newer);
+
+        let (lower, upper) = match (first_id, last_id, position) {
+            (Some(first_id), Some(last_id), _) => (first_id, last_id),
+            (_, _, MessagePosition::After(id)) => (id.saturating_add(1), id),
+            (_, _, MessagePosition::Before(id)) | (_, _, MessagePosition::Around(id)) => (id, id.saturating_sub(1)),
+            (_, _, MessagePosition::Latest) => (i64::MAX, i64::MAX),
+        };
+
+        let (has_more_before, has_more_after): (bool, bool) = sqlx::query_as(
+            r#"
+            SELECT
+                EXISTS (SELECT 1 FROM messages WHERE chat_id = $1 AND id < $2 AND ($4 OR thread_root_id IS NULL)),
+                EXISTS (SELECT 1 FROM messages WHERE chat_id = $1 AND id > $3 AND ($4 OR thread_root_id IS NULL))
+            "#,
+        )
+            .bind(chat_id)
+            .bind(lower)
+            .bind(upper)
+            .bind(include_thread_replies)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(MessagePage {
+            messages,
+            has_more_before,
+            has_more_after,
+        })
+    }
+
+    /// Messages with id less than `before_id`, newest first.
+    async fn fetch_older(
+        &self,
+        chat_id: i64,
+        before_id: i64,
+        limit: i64,
+        include_thread_replies: bool,
+    ) -> Result<Vec<Message>, AppError> {
+        let messages: Vec<Message> = sqlx::query_as(
+            r#"
+            SELECT id, chat_id, user_id, type, content, created_at, thread_root_id, expires_at
+            FROM messages
+            WHERE chat_id = $1 AND id < $2 AND ($4 OR thread_root_id IS NULL)
+            ORDER BY id DESC
+            LIMIT $3
+            "#,
+        )
+            .bind(chat_id)
+            .bind(before_id)
+            .bind(limit)
+            .bind(include_thread_replies)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(messages)
+    }
+
+    /// Messages with id greater than `after_id`, oldest first.
+    async fn fetch_newer(
+        &self,
+        chat_id: i64,
+        after_id: i64,
+        limit: i64,
+        include_thread_replies: bool,
+    ) -> Result<Vec<Message>, AppError> {
+        let messages: Vec<Message> = sqlx::query_as(
             r#"
-            SELECT m.id, m.chat_id, m.user_id, m.type, m.content, m.created_at
+            SELECT id, chat_id, user_id, type, content, created_at, thread_root_id, expires_at
+            FROM messages
+            WHERE chat_id = $1 AND id > $2 AND ($4 OR thread_root_id IS NULL)
+            ORDER BY id ASC
+            LIMIT $3
+            "#,
+        )
+            .bind(chat_id)
+            .bind(after_id)
+            .bind(limit)
+            .bind(include_thread_replies)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(messages)
+    }
+
+    /// Search messages in the chats the user is a member of, newest first.
+    /// Returns at most `limit` hits with id less than `before_id`.
+    pub(crate) async fn search_messages(
+        &self,
+        user_id: UserId,
+        query: &str,
+        filter: &MessageSearchFilter,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<MessageSearchHit>, AppError> {
+        let mut hits: Vec<MessageSearchHit> = sqlx::query_as(
+            r#"
+            SELECT m.id, m.chat_id, m.user_id, m.type, m.content, m.created_at, m.thread_root_id, m.expires_at,
+                ts_headline(
+                    'simple', translate(m.content, $11 || $12, ''), plainto_tsquery('simple', $2),
+                    'StartSel=' || $11 || ', StopSel=' || $12 || ', MaxWords=20, MinWords=5'
+                ) AS snippet
+            FROM messages m
+            JOIN chat_members cm ON m.chat_id = cm.chat_id AND cm.user_id = $1
+            WHERE m.type = COALESCE($8::message_type, 'text')
+            AND (m.search_vector @@ plainto_tsquery('simple', $2) OR m.content ILIKE '%' || $3 || '%')
+            AND ($4::BIGINT IS NULL OR m.chat_id = $4)
+            AND ($5::BIGINT IS NULL OR m.user_id = $5)
+            AND ($6::TIMESTAMPTZ IS NULL OR m.created_at >= $6)
+            AND ($7::TIMESTAMPTZ IS NULL OR m.created_at < $7)
+            AND m.id < $9
+            ORDER BY m.id DESC
+            LIMIT $10
+            "#,
+        )
+            .bind(user_id)
+            .bind(query)
+            .bind(escape_like(query))
+            .bind(filter.chat_id)
+            .bind(filter.sender_id)
+            .bind(filter.from)
+            .bind(filter.to)
+            .bind(filter.r#type)
+            .bind(before_id.unwrap_or(i64::MAX))
+            .bind(limit)
+            .bind(HEADLINE_START)
+            .bind(HEADLINE_STOP)
+            .fetch_all(&self.pool)
+            .await?;
+
+        // the headline is plain text with markers, the snippet is HTML
+        // ts_headline can't find words in CJK text matched by the trigram index
+        for hit in hits.iter_mut() {
+            hit.snippet = if hit.snippet.contains(HEADLINE_START) {
+                render_headline(&hit.snippet)
+            } else {
+                highlight_snippet(&hit.message.content, query, 20)
+                    .unwrap_or_else(|| render_headline(&hit.snippet))
+            };
+        }
+
+        Ok(hits)
+    }
+
+    /// Save a message with its payload and mentions, returning the updated thread too when it is a thread reply.
+    /// Mentioned users must be other members of the chat, only the owner of a group chat can mention `@all`.
+    pub(crate) async fn create_message(
+        &self,
+        message: OutgoingMessage,
+        payload: Option<NewMessagePayload>,
+        mentions: &[UserId],
+        mention_all: bool,
+    ) -> Result<(Message, Option<MessageThread>), AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let ret = insert_message(&mut tx, message, payload, mentions, mention_all).await?;
+
+        tx.commit().await?;
+
+        Ok(ret)
+    }
+
+    /// Replies of a thread with id greater than `after_id`, oldest first.
+    pub(crate) async fn get_thread_replies(
+        &self,
+        chat_id: i64,
+        user_id: UserId,
+        root_id: i64,
+        after_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<Message>, AppError> {
+        let root: Option<(Option<i64>,)> = sqlx::query_as(
+            r#"
+            SELECT m.thread_root_id
             FROM messages m
             JOIN chat_members cm ON m.chat_id = cm.chat_id
-            WHERE m.chat_id = $1 AND cm.user_id = $2
-            AND m.id < $3
-            ORDER BY m.created_at DESC
-            LIMIT 20
+            WHERE m.chat_id = $1 AND m.id = $2 AND cm.user_id = $3
             "#,
         )
             .bind(chat_id)
+            .bind(root_id)
             .bind(user_id)
-            .bind(cursor_id.unwrap_or(i64::MAX))
-            .fetch_all(&self.pool)
+            .fetch_optional(&self.pool)
             .await?;
 
-        messages.reverse();
+        if !matches!(root, Some((None,))) {
+            return Err(AppError::InvalidInput("Message not found".to_string()));
+        }
+
+        let messages: Vec<Message> = sqlx::query_as(
+            r#"
+            SELECT id, chat_id, user_id, type, content, created_at, thread_root_id, expires_at
+            FROM messages
+            WHERE chat_id = $1 AND thread_root_id = $2 AND ($3::BIGINT IS NULL OR id > $3)
+            ORDER BY id ASC
+            LIMIT $4
+            "#,
+        )
+            .bind(chat_id)
+            .bind(root_id)
+            .bind(after_id)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
 
         Ok(messages)
     }
 
-    pub(crate) async fn create_message(&self, chat_id: i64, user_id: UserId, r#type: MessageType, content: String) -> Result<Message, AppError> {
+    pub(crate) async fn get_threads(&self, keys: &[(i64, i64)]) -> Result<Vec<MessageThread>, AppError> {
+        let (chat_ids, root_ids): (Vec<i64>, Vec<i64>) = keys.iter().cloned().unzip();
+
+        let threads: Vec<MessageThread> = sqlx::query_as(
+            r#"
+            SELECT t.chat_id, t.root_id, t.reply_count, t.last_reply_at
+            FROM message_threads t
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, root_id)
+            ON t.chat_id = k.chat_id AND t.root_id = k.root_id
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(root_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(threads)
+    }
+
+    /// Delete up to `limit` messages whose timer ran out, with everything attached to them.
+    /// The replies of an expired thread root go with it.
+    /// Returns the (chat id, message id) of the deleted messages.
+    pub(crate) async fn delete_expired_messages(&self, limit: i64) -> Result<Vec<(i64, i64)>, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let expired: Vec<(i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT chat_id, id FROM messages
+            WHERE expires_at <= now()
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+        )
+            .bind(limit)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        if expired.is_empty() {
+            return Ok(expired);
+        }
+
+        let deleted = delete_messages(&mut tx, &expired).await?;
+
+        tx.commit().await?;
+
+        Ok(deleted.into_iter().map(|m| (m.chat_id, m.id)).collect())
+    }
+
+    /// Move a live location, only its sender can until it expires.
+    pub(crate) async fn update_live_location(
+        &self,
+        chat_id: i64,
+        message_id: i64,
+        user_id: UserId,
+        latitude: f64,
+        longitude: f64,
+    ) -> Result<Message, AppError> {
+        let message: Option<Message> = sqlx::query_as(
+            r#"
+            SELECT id, chat_id, user_id, type, content, created_at, thread_root_id, expires_at
+            FROM messages
+            WHERE chat_id = $1 AND id = $2 AND user_id = $3 AND type = 'location'
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(message) = message else {
+            return Err(AppError::InvalidInput("Message not found".to_string()));
+        };
+
+        let ret = sqlx::query(
+            r#"
+            UPDATE message_locations
+            SET latitude = $3, longitude = $4, updated_at = now()
+            WHERE chat_id = $1 AND message_id = $2 AND live_until > now()
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message_id)
+            .bind(latitude)
+            .bind(longitude)
+            .execute(&self.pool)
+            .await?;
+
+        if ret.rows_affected() == 0 {
+            return Err(AppError::InvalidInput("The location is not live".to_string()));
+        }
+
+        Ok(message)
+    }
+
+    pub(crate) async fn get_locations(&self, keys: &[(i64, i64)]) -> Result<Vec<LocationPayload>, AppError> {
+        let (chat_ids, message_ids): (Vec<i64>, Vec<i64>) = keys.iter().cloned().unzip();
+
+        let locations: Vec<LocationPayload> = sqlx::query_as(
+            r#"
+            SELECT l.chat_id, l.message_id, l.latitude, l.longitude, l.label, l.live_until, l.updated_at
+            FROM message_locations l
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, message_id)
+            ON l.chat_id = k.chat_id AND l.message_id = k.message_id
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(message_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(locations)
+    }
+
+    pub(crate) async fn get_contacts(&self, keys: &[(i64, i64)]) -> Result<Vec<ContactPayload>, AppError> {
+        let (chat_ids, message_ids): (Vec<i64>, Vec<i64>) = keys.iter().cloned().unzip();
+
+        let contacts: Vec<ContactPayload> = sqlx::query_as(
+            r#"
+            SELECT c.chat_id, c.message_id, c.user_id, c.display_name
+            FROM message_contacts c
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, message_id)
+            ON c.chat_id = k.chat_id AND c.message_id = k.message_id
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(message_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(contacts)
+    }
+
+    pub(crate) async fn get_system_events(&self, keys: &[(i64, i64)]) -> Result<Vec<SystemPayload>, AppError> {
+        let (chat_ids, message_ids): (Vec<i64>, Vec<i64>) = keys.iter().cloned().unzip();
+
+        let events: Vec<SystemPayload> = sqlx::query_as(
+            r#"
+            SELECT e.chat_id, e.message_id, e.actor_id, e.action, e.target_ids, e.detail
+            FROM message_system_events e
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, message_id)
+            ON e.chat_id = k.chat_id AND e.message_id = k.message_id
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(message_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(events)
+    }
+
+    pub(crate) async fn save_link_preview(&self, chat_id: i64, message_id: i64, preview: &LinkPreview) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO message_link_previews (chat_id, message_id, url, title, description, image_url, site_name)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (chat_id, message_id) DO NOTHING
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message_id)
+            .bind(&preview.url)
+            .bind(&preview.title)
+            .bind(&preview.description)
+            .bind(&preview.image_url)
+            .bind(&preview.site_name)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn get_link_previews(&self, keys: &[(i64, i64)]) -> Result<Vec<MessageLinkPreview>, AppError> {
+        let (chat_ids, message_ids): (Vec<i64>, Vec<i64>) = keys.iter().cloned().unzip();
+
+        let previews: Vec<MessageLinkPreview> = sqlx::query_as(
+            r#"
+            SELECT p.chat_id, p.message_id, p.url, p.title, p.description, p.image_url, p.site_name
+            FROM message_link_previews p
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, message_id)
+            ON p.chat_id = k.chat_id AND p.message_id = k.message_id
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(message_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(previews)
+    }
+
+    pub(crate) async fn get_mentions(&self, keys: &[(i64, i64)]) -> Result<Vec<MessageMentions>, AppError> {
+        let (chat_ids, message_ids): (Vec<i64>, Vec<i64>) = keys.iter().cloned().unzip();
+
+        let mentions: Vec<MessageMentions> = sqlx::query_as(
+            r#"
+            SELECT mm.chat_id, mm.message_id, mm.user_ids, mm.mention_all
+            FROM message_mentions mm
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, message_id)
+            ON mm.chat_id = k.chat_id AND mm.message_id = k.message_id
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(message_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(mentions)
+    }
+
+    /// In group chats only the owner can pin messages, in private chats both members can.
+    pub(crate) async fn pin_message(&self, chat_id: i64, message_id: i64, user_id: UserId) -> Result<PinnedMessage, AppError> {
+        let mut tx = self.pool.begin().await?;
+
         let chat: Option<Chat> = sqlx::query_as(
             r#"
-            SELECT c.id, c.name, c.owner_id, c.type, c.created_at
+            SELECT c.id, c.name, c.owner_id, c.type, c.created_at, c.last_activity_at, c.block_links, c.message_ttl_seconds
             FROM chats c
             JOIN chat_members cm ON c.id = cm.chat_id
             WHERE c.id = $1 AND cm.user_id = $2
+            FOR UPDATE OF c
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let chat = chat.ok_or(AppError::ChatNotFound)?;
+        if chat.r#type == ChatType::Group && chat.owner_id != user_id {
+            return Err(AppError::ChatError("Only the owner can pin messages".to_string()));
+        }
+
+        let (count,): (i64,) = sqlx::query_as("SELECT count(*) FROM pinned_messages WHERE chat_id = $1")
+            .bind(chat_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        if count >= MAX_PINNED_MESSAGES {
+            return Err(AppError::ChatError(format!("At most {} messages can be pinned", MAX_PINNED_MESSAGES)));
+        }
+
+        let pinned: Option<PinnedMessage> = sqlx::query_as(
+            r#"
+            WITH pinned AS (
+                INSERT INTO pinned_messages (chat_id, message_id, pinned_by)
+                SELECT m.chat_id, m.id, $3
+                FROM messages m
+                WHERE m.chat_id = $1 AND m.id = $2
+                ON CONFLICT (chat_id, message_id) DO UPDATE SET pinned_by = EXCLUDED.pinned_by, pinned_at = now()
+                RETURNING message_id, pinned_by, pinned_at
+            )
+            SELECT m.id, m.chat_id, m.user_id, m.type, m.content, m.created_at, m.thread_root_id, m.expires_at, p.pinned_by, p.pinned_at
+            FROM pinned p
+            JOIN messages m ON m.chat_id = $1 AND m.id = p.message_id
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message_id)
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let pinned = pinned.ok_or(AppError::InvalidInput("Message not found".to_string()))?;
+
+        tx.commit().await?;
+
+        Ok(pinned)
+    }
+
+    pub(crate) async fn unpin_message(&self, chat_id: i64, message_id: i64, user_id: UserId) -> Result<bool, AppError> {
+        let ret = sqlx::query(
+            r#"
+            DELETE FROM pinned_messages p
+            USING chats c, chat_members cm
+            WHERE p.chat_id = $1 AND p.message_id = $2
+            AND c.id = p.chat_id AND cm.chat_id = p.chat_id AND cm.user_id = $3
+            AND (c.type = 'private' OR c.owner_id = $3)
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(ret.rows_affected() == 1)
+    }
+
+    /// Pinned messages of a chat, latest pinned first.
+    pub(crate) async fn get_pinned_messages(&self, chat_id: i64) -> Result<Vec<PinnedMessage>, AppError> {
+        let pinned: Vec<PinnedMessage> = sqlx::query_as(
+            r#"
+            SELECT m.id, m.chat_id, m.user_id, m.type, m.content, m.created_at, m.thread_root_id, m.expires_at, p.pinned_by, p.pinned_at
+            FROM pinned_messages p
+            JOIN messages m ON m.chat_id = p.chat_id AND m.id = p.message_id
+            WHERE p.chat_id = $1
+            ORDER BY p.pinned_at DESC
+            "#,
+        )
+            .bind(chat_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(pinned)
+    }
+
+    pub(crate) async fn star_message(&self, chat_id: i64, message_id: i64, user_id: UserId) -> Result<StarredMessage, AppError> {
+        let starred: Option<StarredMessage> = sqlx::query_as(
+            r#"
+            WITH starred AS (
+                INSERT INTO starred_messages (user_id, chat_id, message_id)
+                SELECT $3, m.chat_id, m.id
+                FROM messages m
+                JOIN chat_members cm ON cm.chat_id = m.chat_id AND cm.user_id = $3
+                WHERE m.chat_id = $1 AND m.id = $2
+                ON CONFLICT (user_id, chat_id, message_id) DO UPDATE SET created_at = starred_messages.created_at
+                RETURNING id, message_id, created_at
+            )
+            SELECT s.id AS star_id, m.id, m.chat_id, m.user_id, m.type, m.content, m.created_at, m.thread_root_id, m.expires_at, s.created_at AS starred_at
+            FROM starred s
+            JOIN messages m ON m.chat_id = $1 AND m.id = s.message_id
             "#,
         )
             .bind(chat_id)
+            .bind(message_id)
             .bind(user_id)
             .fetch_optional(&self.pool)
             .await?;
 
-        if chat.is_none() {
-            return Err(AppError::ChatError("Cant not send message to chat".to_string()));
+        starred.ok_or(AppError::InvalidInput("Message not found".to_string()))
+    }
+
+    pub(crate) async fn unstar_message(&self, chat_id: i64, message_id: i64, user_id: UserId) -> Result<bool, AppError> {
+        let ret = sqlx::query(
+            r#"
+            DELETE FROM starred_messages
+            WHERE user_id = $1 AND chat_id = $2 AND message_id = $3
+            "#,
+        )
+            .bind(user_id)
+            .bind(chat_id)
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(ret.rows_affected() == 1)
+    }
+
+    /// Starred messages in the chats the user is still a member of, latest starred first.
+    pub(crate) async fn get_starred_messages(
+        &self,
+        user_id: UserId,
+        before_star_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<StarredMessage>, AppError> {
+        let starred: Vec<StarredMessage> = sqlx::query_as(
+            r#"
+            SELECT s.id AS star_id, m.id, m.chat_id, m.user_id, m.type, m.content, m.created_at, m.thread_root_id, m.expires_at, s.created_at AS starred_at
+            FROM starred_messages s
+            JOIN chat_members cm ON cm.chat_id = s.chat_id AND cm.user_id = s.user_id
+            JOIN messages m ON m.chat_id = s.chat_id AND m.id = s.message_id
+            WHERE s.user_id = $1 AND ($2::BIGINT IS NULL OR s.id < $2)
+            ORDER BY s.id DESC
+            LIMIT $3
+            "#,
+        )
+            .bind(user_id)
+            .bind(before_star_id)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(starred)
+    }
+
+    /// Messages of a chat the user is a member of, with the origin their forwarded copies record.
+    /// Fails if any of the messages does not exist.
+    pub(crate) async fn get_forward_sources(
+        &self,
+        chat_id: i64,
+        message_ids: &[i64],
+        user_id: UserId,
+    ) -> Result<Vec<ForwardSource>, AppError> {
+        let sources: Vec<ForwardSource> = sqlx::query_as(
+            r#"
+            SELECT m.id, m.chat_id, m.user_id, m.type, m.content, m.created_at, m.thread_root_id, m.expires_at,
+                CASE WHEN f.chat_id IS NULL THEN m.user_id ELSE f.origin_user_id END AS origin_user_id,
+                COALESCE(f.attributed, u.forward_attribution) AS attributed,
+                COALESCE(f.origin_sender_name, u.fullname) AS origin_sender_name,
+                CASE WHEN f.chat_id IS NULL THEN m.chat_id ELSE f.origin_chat_id END AS origin_chat_id,
+                CASE WHEN f.chat_id IS NULL THEN m.id ELSE f.origin_message_id END AS origin_message_id,
+                COALESCE(f.origin_created_at, m.created_at) AS origin_created_at
+            FROM messages m
+            JOIN chat_members cm ON cm.chat_id = m.chat_id AND cm.user_id = $3
+            JOIN users u ON u.id = m.user_id
+            LEFT JOIN message_forwards f ON f.chat_id = m.chat_id AND f.message_id = m.id
+            WHERE m.chat_id = $1 AND m.id = ANY($2)
+            ORDER BY m.id
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message_ids)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        if sources.len() != message_ids.len() {
+            return Err(AppError::InvalidInput("Message not found".to_string()));
+        }
+
+        Ok(sources)
+    }
+
+    /// Save the copies in one transaction, each goes through the checks of a sent message.
+    pub(crate) async fn forward_messages(&self, copies: Vec<ForwardCopy>) -> Result<Vec<Message>, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut messages = Vec::with_capacity(copies.len());
+        for copy in copies {
+            let (message, _) = insert_message(&mut tx, copy.message, None, &[], false).await?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO message_forwards (chat_id, message_id, origin_user_id, attributed, origin_sender_name, origin_chat_id, origin_message_id, origin_created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+            )
+                .bind(message.chat_id)
+                .bind(message.id)
+                .bind(copy.source.origin_user_id)
+                .bind(copy.source.attributed)
+                .bind(copy.source.origin_sender_name)
+                .bind(copy.source.origin_chat_id)
+                .bind(copy.source.origin_message_id)
+                .bind(copy.source.origin_created_at)
+                .execute(&mut *tx)
+                .await?;
+
+            messages.push(message);
+        }
+
+        tx.commit().await?;
+
+        Ok(messages)
+    }
+
+    pub(crate) async fn get_forward_origins(&self, keys: &[(i64, i64)]) -> Result<Vec<ForwardOrigin>, AppError> {
+        let (chat_ids, message_ids): (Vec<i64>, Vec<i64>) = keys.iter().cloned().unzip();
+
+        let origins: Vec<ForwardOrigin> = sqlx::query_as(
+            r#"
+            SELECT f.chat_id, f.message_id, f.origin_user_id, f.attributed, f.origin_sender_name, f.origin_chat_id, f.origin_message_id, f.origin_created_at
+            FROM message_forwards f
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, message_id)
+            ON f.chat_id = k.chat_id AND f.message_id = k.message_id
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(message_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(origins)
+    }
+}
+
+/// Delete messages on the connection with everything attached to them.
+/// The replies of a deleted thread root go with it, deleted replies are taken off the reply count of their thread.
+/// Returns the deleted messages sorted by chat and id, replies included.
+pub(crate) async fn delete_messages(conn: &mut PgConnection, keys: &[(i64, i64)]) -> Result<Vec<Message>, AppError> {
+    let (chat_ids, message_ids): (Vec<i64>, Vec<i64>) = keys.iter().cloned().unzip();
+
+    let mut messages: Vec<Message> = sqlx::query_as(
+        r#"
+        DELETE FROM messages m
+        USING UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, id)
+        WHERE m.chat_id = k.chat_id AND (m.id = k.id OR m.thread_root_id = k.id)
+        RETURNING m.id, m.chat_id, m.user_id, m.type, m.content, m.created_at, m.thread_root_id, m.expires_at
+        "#,
+    )
+        .bind(&chat_ids)
+        .bind(&message_ids)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    if messages.is_empty() {
+        return Ok(messages);
+    }
+
+    messages.sort_unstable_by_key(|m| (m.chat_id, m.id));
+
+    let (chat_ids, message_ids): (Vec<i64>, Vec<i64>) = messages.iter().map(|m| (m.chat_id, m.id)).unzip();
+
+    for table in [
+        "pinned_messages",
+        "starred_messages",
+        "message_mentions",
+        "message_forwards",
+        "polls",
+        "message_link_previews",
+        "message_locations",
+        "message_contacts",
+        "message_system_events",
+        "message_stickers",
+    ] {
+        sqlx::query(&format!(
+            r#"
+            DELETE FROM {table} t
+            USING UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, message_id)
+            WHERE t.chat_id = k.chat_id AND t.message_id = k.message_id
+            "#,
+        ))
+            .bind(&chat_ids)
+            .bind(&message_ids)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    sqlx::query(
+        r#"
+        DELETE FROM message_threads t
+        USING UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, root_id)
+        WHERE t.chat_id = k.chat_id AND t.root_id = k.root_id
+        "#,
+    )
+        .bind(&chat_ids)
+        .bind(&message_ids)
+        .execute(&mut *conn)
+        .await?;
+
+    // the threads of deleted roots are gone already, this only counts off replies of the remaining roots
+    let mut replies: BTreeMap<(i64, i64), i32> = BTreeMap::new();
+    for message in &messages {
+        if let Some(root_id) = message.thread_root_id {
+            *replies.entry((message.chat_id, root_id)).or_default() += 1;
+        }
+    }
+
+    if !replies.is_empty() {
+        let ((chat_ids, root_ids), counts): ((Vec<i64>, Vec<i64>), Vec<i32>) = replies.into_iter().unzip();
+
+        sqlx::query(
+            r#"
+            UPDATE message_threads t
+            SET reply_count = GREATEST(t.reply_count - k.count, 0)
+            FROM UNNEST($1::BIGINT[], $2::BIGINT[], $3::INT[]) AS k(chat_id, root_id, count)
+            WHERE t.chat_id = k.chat_id AND t.root_id = k.root_id
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(root_ids)
+            .bind(counts)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(messages)
+}
+
+/// Save a message on the connection with the checks every sent message goes through:
+/// membership, blocks in private chats, mentions and the thread it replies to.
+pub(crate) async fn insert_message(
+    conn: &mut PgConnection,
+    message: OutgoingMessage,
+    payload: Option<NewMessagePayload>,
+    mentions: &[UserId],
+    mention_all: bool,
+) -> Result<(Message, Option<MessageThread>), AppError> {
+    let OutgoingMessage { chat_id, user_id, r#type, content, thread_root_id } = message;
+
+    let chat: Option<Chat> = sqlx::query_as(
+        r#"
+        SELECT c.id, c.name, c.owner_id, c.type, c.created_at, c.last_activity_at, c.block_links, c.message_ttl_seconds
+        FROM chats c
+        JOIN chat_members cm ON c.id = cm.chat_id
+        WHERE c.id = $1 AND cm.user_id = $2
+        "#,
+    )
+        .bind(chat_id)
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let Some(chat) = chat else {
+        return Err(AppError::ChatError("Cant not send message to chat".to_string()));
+    };
+
+    if chat.r#type == ChatType::Private {
+        let blocked: Option<(UserId,)> = sqlx::query_as(
+            r#"
+            SELECT b.user_id
+            FROM user_blocks b
+            JOIN chat_members cm ON cm.chat_id = $1 AND cm.user_id = b.user_id
+            WHERE b.blocked_id = $2
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        if blocked.is_some() {
+            return Err(AppError::ChatError("Can not send messages to this user".to_string()));
+        }
+    }
+
+    let mut mentions = mentions.to_vec();
+    mentions.sort_unstable();
+    mentions.dedup();
+
+    if mention_all && (chat.r#type != ChatType::Group || chat.owner_id != user_id) {
+        return Err(AppError::ChatError("Only the owner of a group chat can mention all".to_string()));
+    }
+
+    if !mentions.is_empty() {
+        let (members,): (i64,) = sqlx::query_as(
+            r#"
+            SELECT count(*) FROM chat_members WHERE chat_id = $1 AND user_id = ANY($2) AND user_id != $3
+            "#,
+        )
+            .bind(chat_id)
+            .bind(&mentions)
+            .bind(user_id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        if members != mentions.len() as i64 {
+            return Err(AppError::InvalidInput("Mentioned users must be other members of the chat".to_string()));
+        }
+    }
+
+    if let Some(root_id) = thread_root_id {
+        if chat.r#type != ChatType::Group {
+            return Err(AppError::ChatError("Threads are only available in group chats".to_string()));
         }
 
-        let message: Message = sqlx::query_as(
+        // lock the root so it can not be deleted while the reply is saved
+        let root: Option<(Option<i64>,)> = sqlx::query_as(
             r#"
-            INSERT INTO messages (chat_id, user_id, type, content)
+            SELECT thread_root_id FROM messages WHERE chat_id = $1 AND id = $2 FOR SHARE
+            "#,
+        )
+            .bind(chat_id)
+            .bind(root_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        match root {
+            None => return Err(AppError::InvalidInput("Message not found".to_string())),
+            Some((Some(_),)) => return Err(AppError::InvalidInput("Can not reply in a thread to a thread reply".to_string())),
+            Some((None,)) => {}
+        }
+    }
+
+    let message: Message = sqlx::query_as(
+        r#"
+        INSERT INTO messages (chat_id, user_id, type, content, thread_root_id)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, chat_id, user_id, type, content, created_at, thread_root_id, expires_at
+        "#,
+    )
+        .bind(chat_id)
+        .bind(user_id)
+        .bind(r#type)
+        .bind(content)
+        .bind(thread_root_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    match payload {
+        Some(NewMessagePayload::Location { latitude, longitude, label, live_until }) => {
+            sqlx::query(
+                r#"
+                INSERT INTO message_locations (chat_id, message_id, latitude, longitude, label, live_until)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+                .bind(chat_id)
+                .bind(message.id)
+                .bind(latitude)
+                .bind(longitude)
+                .bind(label)
+                .bind(live_until)
+                .execute(&mut *conn)
+                .await?;
+        }
+        Some(NewMessagePayload::Contact { user_id, display_name }) => {
+            sqlx::query(
+                r#"
+                INSERT INTO message_contacts (chat_id, message_id, user_id, display_name)
+                VALUES ($1, $2, $3, $4)
+                "#,
+            )
+                .bind(chat_id)
+                .bind(message.id)
+                .bind(user_id)
+                .bind(display_name)
+                .execute(&mut *conn)
+                .await?;
+        }
+        Some(NewMessagePayload::Sticker { sticker_id }) => {
+            sqlx::query(
+                r#"
+                INSERT INTO message_stickers (chat_id, message_id, sticker_id)
+                VALUES ($1, $2, $3)
+                "#,
+            )
+                .bind(chat_id)
+                .bind(message.id)
+                .bind(sticker_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+        None => {}
+    }
+
+    let thread: Option<MessageThread> = match thread_root_id {
+        Some(root_id) => Some(sqlx::query_as(
+            r#"
+            INSERT INTO message_threads (chat_id, root_id, reply_count, last_reply_at)
+            VALUES ($1, $2, 1, $3)
+            ON CONFLICT (chat_id, root_id) DO UPDATE
+            SET reply_count = message_threads.reply_count + 1, last_reply_at = EXCLUDED.last_reply_at
+            RETURNING chat_id, root_id, reply_count, last_reply_at
+            "#,
+        )
+            .bind(chat_id)
+            .bind(root_id)
+            .bind(message.created_at)
+            .fetch_one(&mut *conn)
+            .await?),
+        None => None,
+    };
+
+    if mention_all || !mentions.is_empty() {
+        sqlx::query(
+            r#"
+            INSERT INTO message_mentions (chat_id, message_id, user_ids, mention_all)
             VALUES ($1, $2, $3, $4)
-            RETURNING id, chat_id, user_id, type, content, created_at
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message.id)
+            .bind(&mentions)
+            .bind(mention_all)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE chat_members
+            SET unread_mention_count = unread_mention_count + 1
+            WHERE chat_id = $1 AND user_id != $2 AND ($3 OR user_id = ANY($4))
             "#,
         )
             .bind(chat_id)
             .bind(user_id)
-            .bind(r#type)
-            .bind(content)
-            .fetch_one(&self.pool)
+            .bind(mention_all)
+            .bind(&mentions)
+            .execute(&mut *conn)
             .await?;
+    }
 
-        Ok(message)
+    Ok((message, thread))
+}
+
+/// Save a system message on the connection of the change it describes, so both are committed together.
+/// The message is sent by the actor and its content is an english fallback for clients that do not localize it.
+pub(crate) async fn insert_system_message(
+    conn: &mut PgConnection,
+    chat_id: i64,
+    event: NewSystemEvent,
+) -> Result<Message, AppError> {
+    let mut user_ids = event.target_ids.clone();
+    user_ids.push(event.actor_id);
+
+    let names: Vec<(UserId, String)> = sqlx::query_as("SELECT id, fullname FROM users WHERE id = ANY($1)")
+        .bind(&user_ids)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    let name_of = |id: UserId| names.iter()
+        .find(|(user_id, _)| *user_id == id)
+        .map(|(_, name)| name.clone())
+        .unwrap_or_else(|| "Deleted user".to_string());
+
+    let actor = name_of(event.actor_id);
+    let targets: Vec<String> = event.target_ids.iter().map(|id| name_of(*id)).collect();
+    let content = system_message_text(event.action, &actor, &targets, event.detail.as_deref());
+
+    let message: Message = sqlx::query_as(
+        r#"
+        INSERT INTO messages (chat_id, user_id, type, content)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, chat_id, user_id, type, content, created_at, thread_root_id, expires_at
+        "#,
+    )
+        .bind(chat_id)
+        .bind(event.actor_id)
+        .bind(MessageType::System)
+        .bind(content)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO message_system_events (chat_id, message_id, actor_id, action, target_ids, detail)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+        .bind(chat_id)
+        .bind(message.id)
+        .bind(event.actor_id)
+        .bind(event.action)
+        .bind(event.target_ids)
+        .bind(event.detail)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(message)
+}
+
+fn system_message_text(action: SystemAction, actor: &str, targets: &[String], detail: Option<&str>) -> String {
+    match action {
+        SystemAction::GroupCreated => format!("{} created the group \"{}\"", actor, detail.unwrap_or_default()),
+        SystemAction::GroupRenamed => format!("{} renamed the group to \"{}\"", actor, detail.unwrap_or_default()),
+        SystemAction::MemberLeft => format!("{} left the group", actor),
+        SystemAction::OwnerChanged => format!("{} made {} the owner", actor, targets.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::repository::ChatRepository;
+    use super::*;
+
+    async fn connect() -> PgPool {
+        let config = AppConfig::load();
+
+        PgPool::connect(config.server.postgres_url.as_str())
+            .await
+            .unwrap()
+    }
+
+    async fn create_user(pool: &PgPool) -> UserId {
+        let (id,): (UserId,) = sqlx::query_as("INSERT INTO users (fullname, email, password_hash) VALUES ('message', $1, '') RETURNING id")
+            .bind(format!("message-{}@test.local", uuid::Uuid::now_v7()))
+            .fetch_one(pool)
+            .await
+            .unwrap();
+
+        id
+    }
+
+    async fn send(repo: &MessageRepository, chat_id: i64, user_id: UserId, thread_root_id: Option<i64>) -> Message {
+        let message = OutgoingMessage {
+            chat_id,
+            user_id,
+            r#type: MessageType::Text,
+            content: "hello".to_string(),
+            thread_root_id,
+        };
+
+        repo.create_message(message, None, &[], false).await.unwrap().0
+    }
+
+    async fn set_expires_at(pool: &PgPool, message: &Message, expires_at: DateTime<Utc>) {
+        sqlx::query("UPDATE messages SET expires_at = $3 WHERE chat_id = $1 AND id = $2")
+            .bind(message.chat_id)
+            .bind(message.id)
+            .bind(expires_at)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn delete_expired_messages_should_only_delete_expired_messages() {
+        let pool = connect().await;
+        let repo = MessageRepository::new(pool.clone());
+        let owner = create_user(&pool).await;
+        let members = vec![create_user(&pool).await, create_user(&pool).await];
+        let chat = ChatRepository::new(pool.clone(), false).create(owner, members.clone(), "Expiry".to_string()).await.unwrap();
+
+        let expired = send(&repo, chat.id, owner, None).await;
+        let reply = send(&repo, chat.id, members[0], Some(expired.id)).await;
+        let expiring = send(&repo, chat.id, members[1], None).await;
+        let kept = send(&repo, chat.id, owner, None).await;
+        set_expires_at(&pool, &expired, Utc::now() - chrono::Duration::seconds(1)).await;
+        set_expires_at(&pool, &expiring, Utc::now() + chrono::Duration::hours(1)).await;
+
+        let mut deleted: Vec<i64> = repo.delete_expired_messages(1000).await.unwrap()
+            .into_iter()
+            .filter(|(chat_id, _)| *chat_id == chat.id)
+            .map(|(_, id)| id)
+            .collect();
+        deleted.sort_unstable();
+
+        // the reply goes with its expired thread root
+        assert_eq!(deleted, [expired.id, reply.id]);
+
+        let (left,): (Vec<i64>,) = sqlx::query_as("SELECT COALESCE(array_agg(id ORDER BY id), '{}') FROM messages WHERE chat_id = $1 AND type = 'text'")
+            .bind(chat.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, [expiring.id, kept.id]);
+
+        let threads: Vec<MessageThread> = repo.get_threads(&[(chat.id, expired.id)]).await.unwrap();
+        assert!(threads.is_empty());
+    }
+
+    #[test]
+    fn system_message_text_should_describe_the_change() {
+        assert_eq!(
+            system_message_text(SystemAction::GroupRenamed, "Alice", &[], Some("Weekend")),
+            "Alice renamed the group to \"Weekend\""
+        );
+        assert_eq!(
+            system_message_text(SystemAction::OwnerChanged, "Deleted user", &["Bob".to_string()], None),
+            "Deleted user made Bob the owner"
+        );
+        assert_eq!(system_message_text(SystemAction::MemberLeft, "Carol", &[], None), "Carol left the group");
     }
 }
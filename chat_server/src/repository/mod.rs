@@ -1,7 +1,21 @@
 mod user;
 mod chat;
 mod message;
+mod contact;
+mod report;
+mod account;
+mod admin;
+mod schedule;
+mod poll;
+mod sticker;
 
 pub(crate) use user::*;
 pub(crate) use chat::*;
 pub(crate) use message::*;
+pub(crate) use contact::*;
+pub(crate) use report::*;
+pub(crate) use account::*;
+pub(crate) use admin::*;
+pub(crate) use schedule::*;
+pub(crate) use poll::*;
+pub(crate) use sticker::*;
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use crate::error::AppError;
+use crate::models::{ChatType, Message, MessageType, Poll, PollOption, PollRow, UserId};
+
+/// A poll about to be sent, after the message filters ran on its question and options.
+#[derive(Debug, Clone)]
+pub(crate) struct NewPoll {
+    pub(crate) chat_id: i64,
+    pub(crate) question: String,
+    pub(crate) options: Vec<String>,
+    pub(crate) multi_choice: bool,
+    pub(crate) anonymous: bool,
+    pub(crate) closes_at: Option<DateTime<Utc>>,
+}
+
+pub struct PollRepository {
+    pub(crate) pool: PgPool,
+}
+
+impl PollRepository {
+    pub(crate) fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+        }
+    }
+
+    /// Send a poll to a group chat, as a message of type poll whose content is the question.
+    pub(crate) async fn create_poll(&self, user_id: UserId, poll: NewPoll) -> Result<Message, AppError> {
+        let NewPoll { chat_id, question, options, multi_choice, anonymous, closes_at } = poll;
+        let mut tx = self.pool.begin().await?;
+
+        let chat_type: Option<(ChatType,)> = sqlx::query_as(
+            r#"
+            SELECT c.type
+            FROM chats c
+            JOIN chat_members cm ON c.id = cm.chat_id
+            WHERE c.id = $1 AND cm.user_id = $2
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        match chat_type {
+            None => return Err(AppError::ChatError("Cant not send message to chat".to_string())),
+            Some((ChatType::Private,)) => return Err(AppError::ChatError("Polls are only available in group chats".to_string())),
+            Some((ChatType::Group,)) => {}
+        }
+
+        let message: Message = sqlx::query_as(
+            r#"
+            INSERT INTO messages (chat_id, user_id, type, content)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, chat_id, user_id, type, content, created_at, thread_root_id, expires_at
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .bind(MessageType::Poll)
+            .bind(question)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO polls (chat_id, message_id, created_by, multi_choice, anonymous, closes_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message.id)
+            .bind(user_id)
+            .bind(multi_choice)
+            .bind(anonymous)
+            .bind(closes_at)
+            .execute(&mut *tx)
+            .await?;
+
+        let option_ids: Vec<i32> = (0..options.len() as i32).collect();
+        sqlx::query(
+            r#"
+            INSERT INTO poll_options (chat_id, message_id, option_id, text)
+            SELECT $1, $2, o.option_id, o.text
+            FROM UNNEST($3::INT[], $4::TEXT[]) AS o(option_id, text)
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message.id)
+            .bind(option_ids)
+            .bind(options)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(message)
+    }
+
+    pub(crate) async fn get_polls(&self, keys: &[(i64, i64)]) -> Result<Vec<Poll>, AppError> {
+        let (chat_ids, message_ids): (Vec<i64>, Vec<i64>) = keys.iter().cloned().unzip();
+
+        let polls: Vec<PollRow> = sqlx::query_as(
+            r#"
+            SELECT p.chat_id, p.message_id, p.created_by, p.multi_choice, p.anonymous, p.closes_at, p.closed_at
+            FROM polls p
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, message_id)
+            ON p.chat_id = k.chat_id AND p.message_id = k.message_id
+            "#,
+        )
+            .bind(&chat_ids)
+            .bind(&message_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        // voters of each option, in the order they voted
+        let options: Vec<(i64, i64, i32, String, Vec<UserId>)> = sqlx::query_as(
+            r#"
+            SELECT o.chat_id, o.message_id, o.option_id, o.text,
+                COALESCE(array_agg(v.user_id ORDER BY v.voted_at, v.user_id) FILTER (WHERE v.user_id IS NOT NULL), '{}') AS voter_ids
+            FROM poll_options o
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, message_id)
+            ON o.chat_id = k.chat_id AND o.message_id = k.message_id
+            LEFT JOIN poll_votes v
+            ON v.chat_id = o.chat_id AND v.message_id = o.message_id AND v.option_id = o.option_id
+            GROUP BY o.chat_id, o.message_id, o.option_id, o.text
+            ORDER BY o.chat_id, o.message_id, o.option_id
+            "#,
+        )
+            .bind(&chat_ids)
+            .bind(&message_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut options_by_poll: HashMap<(i64, i64), Vec<(i32, String, Vec<UserId>)>> = HashMap::new();
+        for (chat_id, message_id, option_id, text, voter_ids) in options {
+            options_by_poll.entry((chat_id, message_id)).or_default().push((option_id, text, voter_ids));
+        }
+
+        let polls = polls
+            .into_iter()
+            .map(|p| {
+                let options = options_by_poll
+                    .remove(&(p.chat_id, p.message_id))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(option_id, text, voter_ids)| PollOption {
+                        option_id,
+                        text,
+                        vote_count: voter_ids.len() as i32,
+                        voter_ids,
+                        anonymous: p.anonymous,
+                    })
+                    .collect();
+
+                Poll {
+                    chat_id: p.chat_id,
+                    message_id: p.message_id,
+                    created_by: p.created_by,
+                    multi_choice: p.multi_choice,
+                    anonymous: p.anonymous,
+                    closes_at: p.closes_at,
+                    closed_at: p.closed_at,
+                    options,
+                }
+            })
+            .collect();
+
+        Ok(polls)
+    }
+
+    /// A poll in a chat the user is a member of.
+    pub(crate) async fn get_poll(&self, chat_id: i64, message_id: i64, user_id: UserId) -> Result<Poll, AppError> {
+        let is_member: Option<(i64,)> = sqlx::query_as(
+            r#"
+            SELECT chat_id
+            FROM chat_members
+            WHERE chat_id = $1 AND user_id = $2
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        if is_member.is_none() {
+            return Err(AppError::ChatNotFound);
+        }
+
+        let poll = self.get_polls(&[(chat_id, message_id)]).await?.pop();
+
+        poll.ok_or(AppError::InvalidInput("Poll not found".to_string()))
+    }
+
+    /// Replace the votes of the user, no option retracts them.
+    pub(crate) async fn vote(
+        &self,
+        chat_id: i64,
+        message_id: i64,
+        user_id: UserId,
+        option_ids: &[i32],
+    ) -> Result<Poll, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        if !is_member(&mut tx, chat_id, user_id).await? {
+            return Err(AppError::ChatNotFound);
+        }
+
+        // lock the poll, so it can not be closed while the votes are saved
+        let poll: Option<(bool, Option<DateTime<Utc>>, Option<DateTime<Utc>>, Vec<i32>)> = sqlx::query_as(
+            r#"
+            SELECT p.multi_choice, p.closes_at, p.closed_at,
+                ARRAY(SELECT o.option_id FROM poll_options o WHERE o.chat_id = p.chat_id AND o.message_id = p.message_id)
+            FROM polls p
+            WHERE p.chat_id = $1 AND p.message_id = $2
+            FOR UPDATE
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let Some((multi_choice, closes_at, closed_at, valid_ids)) = poll else {
+            return Err(AppError::InvalidInput("Poll not found".to_string()));
+        };
+
+        if closed_at.is_some() || closes_at.is_some_and(|t| t <= Utc::now()) {
+            return Err(AppError::InvalidInput("The poll is closed".to_string()));
+        }
+
+        if !multi_choice && option_ids.len() > 1 {
+            return Err(AppError::InvalidInput("Only one option can be chosen".to_string()));
+        }
+
+        if option_ids.iter().any(|id| !valid_ids.contains(id)) {
+            return Err(AppError::InvalidInput("Invalid poll option".to_string()));
+        }
+
+        sqlx::query(
+            r#"
+            DELETE FROM poll_votes WHERE chat_id = $1 AND message_id = $2 AND user_id = $3
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO poll_votes (chat_id, message_id, option_id, user_id)
+            SELECT $1, $2, o.option_id, $3
+            FROM UNNEST($4::INT[]) AS o(option_id)
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message_id)
+            .bind(user_id)
+            .bind(option_ids)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        self.get_poll(chat_id, message_id, user_id).await
+    }
+
+    /// Only the creator of the poll or the owner of the chat can close it.
+    pub(crate) async fn close_poll(&self, chat_id: i64, message_id: i64, user_id: UserId) -> Result<Poll, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        if !is_member(&mut tx, chat_id, user_id).await? {
+            return Err(AppError::ChatNotFound);
+        }
+
+        let ret = sqlx::query(
+            r#"
+            UPDATE polls p
+            SET closed_at = now()
+            FROM chats c
+            WHERE p.chat_id = $1 AND p.message_id = $2 AND c.id = p.chat_id
+                AND p.closed_at IS NULL AND (p.created_by = $3 OR c.owner_id = $3)
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        let poll = self.get_poll(chat_id, message_id, user_id).await?;
+
+        if ret.rows_affected() == 0 && poll.closed_at.is_none() {
+            return Err(AppError::Forbidden);
+        }
+
+        Ok(poll)
+    }
+}
+
+async fn is_member(conn: &mut PgConnection, chat_id: i64, user_id: UserId) -> Result<bool, AppError> {
+    let member: Option<(i64,)> = sqlx::query_as("SELECT chat_id FROM chat_members WHERE chat_id = $1 AND user_id = $2")
+        .bind(chat_id)
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    Ok(member.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use crate::config::AppConfig;
+    use crate::repository::ChatRepository;
+    use super::*;
+
+    async fn connect() -> PgPool {
+        let config = AppConfig::load();
+
+        PgPool::connect(config.server.postgres_url.as_str())
+            .await
+            .unwrap()
+    }
+
+    async fn create_user(pool: &PgPool) -> UserId {
+        let (id,): (UserId,) = sqlx::query_as("INSERT INTO users (fullname, email, password_hash) VALUES ('poll', $1, '') RETURNING id")
+            .bind(format!("poll-{}@test.local", uuid::Uuid::now_v7()))
+            .fetch_one(pool)
+            .await
+            .unwrap();
+
+        id
+    }
+
+    /// A group of the owner and two members, with a poll of three options sent by the first member.
+    async fn create_poll(pool: &PgPool, multi_choice: bool, closes_at: Option<DateTime<Utc>>) -> (Message, UserId, [UserId; 2]) {
+        let owner = create_user(pool).await;
+        let members = [create_user(pool).await, create_user(pool).await];
+        let chat = ChatRepository::new(pool.clone(), false)
+            .create(owner, members.to_vec(), "Polls".to_string())
+            .await
+            .unwrap();
+
+        let poll = NewPoll {
+            chat_id: chat.id,
+            question: "Lunch?".to_string(),
+            options: vec!["Pizza".to_string(), "Sushi".to_string(), "Salad".to_string()],
+            multi_choice,
+            anonymous: false,
+            closes_at,
+        };
+        let message = PollRepository::new(pool.clone()).create_poll(members[0], poll).await.unwrap();
+
+        (message, owner, members)
+    }
+
+    fn votes(poll: &Poll) -> Vec<i32> {
+        poll.options.iter().map(|o| o.vote_count).collect()
+    }
+
+    #[tokio::test]
+    async fn vote_should_allow_several_options_only_in_multi_choice_polls() {
+        let pool = connect().await;
+        let repo = PollRepository::new(pool.clone());
+
+        let (single, _, members) = create_poll(&pool, false, None).await;
+        let ret = repo.vote(single.chat_id, single.id, members[1], &[0, 1]).await;
+        assert!(matches!(ret, Err(AppError::InvalidInput(_))), "{:?}", ret);
+        let poll = repo.vote(single.chat_id, single.id, members[1], &[1]).await.unwrap();
+        assert_eq!(votes(&poll), [0, 1, 0]);
+
+        let (multi, _, members) = create_poll(&pool, true, None).await;
+        let poll = repo.vote(multi.chat_id, multi.id, members[1], &[0, 2]).await.unwrap();
+        assert_eq!(votes(&poll), [1, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn vote_should_reject_invalid_options() {
+        let pool = connect().await;
+        let repo = PollRepository::new(pool.clone());
+        let (message, _, members) = create_poll(&pool, true, None).await;
+
+        for option_ids in [&[3][..], &[-1], &[0, 7]] {
+            let ret = repo.vote(message.chat_id, message.id, members[1], option_ids).await;
+            assert!(matches!(ret, Err(AppError::InvalidInput(_))), "{:?} {:?}", option_ids, ret);
+        }
+
+        let poll = repo.get_poll(message.chat_id, message.id, members[1]).await.unwrap();
+        assert_eq!(votes(&poll), [0, 0, 0]);
+    }
+
+    #[tokio::test]
+    async fn vote_should_fail_on_closed_polls() {
+        let pool = connect().await;
+        let repo = PollRepository::new(pool.clone());
+
+        let (expired, _, members) = create_poll(&pool, false, Some(Utc::now() - Duration::seconds(1))).await;
+        let ret = repo.vote(expired.chat_id, expired.id, members[1], &[0]).await;
+        assert!(matches!(ret, Err(AppError::InvalidInput(_))), "{:?}", ret);
+
+        let (closed, _, members) = create_poll(&pool, false, Some(Utc::now() + Duration::hours(1))).await;
+        repo.close_poll(closed.chat_id, closed.id, members[0]).await.unwrap();
+        let ret = repo.vote(closed.chat_id, closed.id, members[1], &[0]).await;
+        assert!(matches!(ret, Err(AppError::InvalidInput(_))), "{:?}", ret);
+    }
+
+    #[tokio::test]
+    async fn close_poll_should_only_allow_the_creator_or_the_owner() {
+        let pool = connect().await;
+        let repo = PollRepository::new(pool.clone());
+        let (message, owner, members) = create_poll(&pool, false, None).await;
+
+        let ret = repo.close_poll(message.chat_id, message.id, members[1]).await;
+        assert!(matches!(ret, Err(AppError::Forbidden)), "{:?}", ret);
+
+        let outsider = create_user(&pool).await;
+        let ret = repo.close_poll(message.chat_id, message.id, outsider).await;
+        assert!(matches!(ret, Err(AppError::ChatNotFound)), "{:?}", ret);
+
+        let poll = repo.close_poll(message.chat_id, message.id, owner).await.unwrap();
+        assert!(poll.closed_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn vote_without_options_should_retract_the_votes() {
+        let pool = connect().await;
+        let repo = PollRepository::new(pool.clone());
+        let (message, _, members) = create_poll(&pool, true, None).await;
+
+        repo.vote(message.chat_id, message.id, members[1], &[0, 1]).await.unwrap();
+        let poll = repo.vote(message.chat_id, message.id, members[1], &[]).await.unwrap();
+
+        assert_eq!(votes(&poll), [0, 0, 0]);
+    }
+}
@@ -0,0 +1,123 @@
+use serde_json::json;
+use sqlx::PgPool;
+use crate::error::AppError;
+use crate::models::{Message, Report, ReportReason, UserId};
+
+pub struct ReportRepository {
+    pub(crate) pool: PgPool,
+}
+
+impl ReportRepository {
+    pub(crate) fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+        }
+    }
+
+    /// Report a user, optionally pointing at a chat the reporter is a member of.
+    pub(crate) async fn report_user(
+        &self,
+        reporter_id: UserId,
+        target_user_id: UserId,
+        chat_id: Option<i64>,
+        reason: ReportReason,
+        description: Option<String>,
+    ) -> Result<Report, AppError> {
+        if reporter_id == target_user_id {
+            return Err(AppError::InvalidInput("Can not report yourself".to_string()));
+        }
+
+        if let Some(chat_id) = chat_id {
+            let is_member: Option<(i64,)> = sqlx::query_as(
+                r#"
+                SELECT chat_id
+                FROM chat_members
+                WHERE chat_id = $1 AND user_id = $2
+                "#,
+            )
+                .bind(chat_id)
+                .bind(reporter_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+            if is_member.is_none() {
+                return Err(AppError::ChatNotFound);
+            }
+        }
+
+        let report: Report = sqlx::query_as(
+            r#"
+            INSERT INTO reports (reporter_id, target_user_id, chat_id, reason, description, context)
+            SELECT $1, u.id, $3, $4, $5, json_build_object('fullname', u.fullname, 'avatar', u.avatar)
+            FROM users u
+            WHERE u.id = $2
+            RETURNING id, reporter_id, target_user_id, chat_id, message_id, reason, description, context, status, created_at, handled_by, handled_at
+            "#,
+        )
+            .bind(reporter_id)
+            .bind(target_user_id)
+            .bind(chat_id)
+            .bind(reason)
+            .bind(description)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        Ok(report)
+    }
+
+    /// Report a message in a chat the reporter is a member of, the message is copied into the report.
+    pub(crate) async fn report_message(
+        &self,
+        reporter_id: UserId,
+        chat_id: i64,
+        message_id: i64,
+        reason: ReportReason,
+        description: Option<String>,
+    ) -> Result<Report, AppError> {
+        let message: Option<Message> = sqlx::query_as(
+            r#"
+            SELECT m.id, m.chat_id, m.user_id, m.type, m.content, m.created_at, m.thread_root_id, m.expires_at
+            FROM messages m
+            JOIN chat_members cm ON m.chat_id = cm.chat_id
+            WHERE m.chat_id = $1 AND m.id = $2 AND cm.user_id = $3
+            "#,
+        )
+            .bind(chat_id)
+            .bind(message_id)
+            .bind(reporter_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let message = message.ok_or(AppError::InvalidInput("Message not found".to_string()))?;
+
+        if message.user_id == reporter_id {
+            return Err(AppError::InvalidInput("Can not report your own message".to_string()));
+        }
+
+        let context = json!({
+            "type": message.r#type,
+            "content": message.content,
+            "createdAt": message.created_at,
+        });
+
+        let report: Report = sqlx::query_as(
+            r#"
+            INSERT INTO reports (reporter_id, target_user_id, chat_id, message_id, reason, description, context)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, reporter_id, target_user_id, chat_id, message_id, reason, description, context, status, created_at, handled_by, handled_at
+            "#,
+        )
+            .bind(reporter_id)
+            .bind(message.user_id)
+            .bind(chat_id)
+            .bind(message_id)
+            .bind(reason)
+            .bind(description)
+            .bind(context)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,265 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use crate::error::AppError;
+use crate::filter::OutgoingMessage;
+use crate::models::{Message, ScheduledMessage, ScheduledMessageStatus, UserId};
+use crate::repository::insert_message;
+
+const MAX_PENDING_SCHEDULED_MESSAGES: i64 = 100;
+/// Seconds a claimed message is left to its scheduler before another one can claim it again.
+const SCHEDULED_MESSAGE_CLAIM_SECONDS: i64 = 300;
+
+pub struct ScheduleRepository {
+    pub(crate) pool: PgPool,
+}
+
+impl ScheduleRepository {
+    pub(crate) fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+        }
+    }
+
+    pub(crate) async fn create_scheduled_message(
+        &self,
+        chat_id: i64,
+        user_id: UserId,
+        content: String,
+        send_at: DateTime<Utc>,
+    ) -> Result<ScheduledMessage, AppError> {
+        let (is_member, pending): (bool, i64) = sqlx::query_as(
+            r#"
+            SELECT
+                EXISTS (SELECT 1 FROM chat_members WHERE chat_id = $1 AND user_id = $2),
+                (SELECT count(*) FROM scheduled_messages WHERE user_id = $2 AND status = 'pending')
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        if !is_member {
+            return Err(AppError::ChatNotFound);
+        }
+
+        if pending >= MAX_PENDING_SCHEDULED_MESSAGES {
+            return Err(AppError::InvalidInput(format!("At most {} messages can be scheduled", MAX_PENDING_SCHEDULED_MESSAGES)));
+        }
+
+        let message: ScheduledMessage = sqlx::query_as(
+            r#"
+            INSERT INTO scheduled_messages (chat_id, user_id, content, send_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, chat_id, user_id, content, send_at, status, message_id, failure_reason, created_at
+            "#,
+        )
+            .bind(chat_id)
+            .bind(user_id)
+            .bind(content)
+            .bind(send_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(message)
+    }
+
+    /// Scheduled messages of the user sorted by send time, optionally only those of a chat.
+    pub(crate) async fn get_scheduled_messages(
+        &self,
+        user_id: UserId,
+        chat_id: Option<i64>,
+        status: ScheduledMessageStatus,
+    ) -> Result<Vec<ScheduledMessage>, AppError> {
+        let messages: Vec<ScheduledMessage> = sqlx::query_as(
+            r#"
+            SELECT id, chat_id, user_id, content, send_at, status, message_id, failure_reason, created_at
+            FROM scheduled_messages
+            WHERE user_id = $1 AND ($2::BIGINT IS NULL OR chat_id = $2) AND status = $3
+            ORDER BY send_at ASC, id ASC
+            "#,
+        )
+            .bind(user_id)
+            .bind(chat_id)
+            .bind(status)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(messages)
+    }
+
+    pub(crate) async fn cancel_scheduled_message(&self, id: i64, user_id: UserId) -> Result<ScheduledMessage, AppError> {
+        let message: Option<ScheduledMessage> = sqlx::query_as(
+            r#"
+            UPDATE scheduled_messages
+            SET status = 'cancelled'
+            WHERE id = $1 AND user_id = $2 AND status = 'pending'
+            RETURNING id, chat_id, user_id, content, send_at, status, message_id, failure_reason, created_at
+            "#,
+        )
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        message.ok_or(AppError::ScheduledMessageNotFound)
+    }
+
+    /// Claim the pending messages which are due, so no other scheduler sends them meanwhile.
+    /// Messages whose claim expired are claimed again, their scheduler stopped before sending them.
+    pub(crate) async fn claim_due_messages(&self, limit: i64) -> Result<Vec<ScheduledMessage>, AppError> {
+        let messages: Vec<ScheduledMessage> = sqlx::query_as(
+            r#"
+            UPDATE scheduled_messages
+            SET status = 'sending', claimed_at = now()
+            WHERE id IN (
+                SELECT id FROM scheduled_messages
+                WHERE (status = 'pending' AND send_at <= now())
+                OR (status = 'sending' AND claimed_at <= now() - make_interval(secs => $2))
+                ORDER BY send_at ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, chat_id, user_id, content, send_at, status, message_id, failure_reason, created_at
+            "#,
+        )
+            .bind(limit)
+            .bind(SCHEDULED_MESSAGE_CLAIM_SECONDS as f64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(messages)
+    }
+
+    /// Save the message of a claimed scheduled message and mark it sent in one transaction,
+    /// so it is either sent once or left to be claimed again.
+    /// Returns `None` when another scheduler sent it already.
+    pub(crate) async fn send_claimed_message(&self, id: i64, message: OutgoingMessage) -> Result<Option<Message>, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let claimed: Option<(i64,)> = sqlx::query_as("SELECT id FROM scheduled_messages WHERE id = $1 AND status = 'sending' FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        if claimed.is_none() {
+            return Ok(None);
+        }
+
+        let (message, _) = insert_message(&mut tx, message, None, &[], false).await?;
+
+        sqlx::query("UPDATE scheduled_messages SET status = 'sent', message_id = $2 WHERE id = $1")
+            .bind(id)
+            .bind(message.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(message))
+    }
+
+    pub(crate) async fn mark_failed(&self, id: i64, reason: String) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE scheduled_messages SET status = 'failed', failure_reason = $2 WHERE id = $1 AND status = 'sending'
+            "#,
+        )
+            .bind(id)
+            .bind(reason)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use crate::config::AppConfig;
+    use crate::models::MessageType;
+    use crate::repository::ChatRepository;
+    use super::*;
+
+    async fn connect() -> PgPool {
+        let config = AppConfig::load();
+
+        PgPool::connect(config.server.postgres_url.as_str())
+            .await
+            .unwrap()
+    }
+
+    async fn create_user(pool: &PgPool) -> UserId {
+        let (id,): (UserId,) = sqlx::query_as("INSERT INTO users (fullname, email, password_hash) VALUES ('schedule', $1, '') RETURNING id")
+            .bind(format!("schedule-{}@test.local", uuid::Uuid::now_v7()))
+            .fetch_one(pool)
+            .await
+            .unwrap();
+
+        id
+    }
+
+    fn claimed_ids(messages: &[ScheduledMessage]) -> Vec<i64> {
+        messages.iter().map(|m| m.id).collect()
+    }
+
+    #[tokio::test]
+    async fn claim_due_messages_should_skip_claimed_messages_until_the_claim_expires() {
+        let pool = connect().await;
+        let repo = ScheduleRepository::new(pool.clone());
+        let (me, friend) = (create_user(&pool).await, create_user(&pool).await);
+        let chat = ChatRepository::new(pool.clone(), false).create(me, vec![friend], "".to_string()).await.unwrap();
+
+        let due = repo.create_scheduled_message(chat.id, me, "due".to_string(), Utc::now() - Duration::seconds(1)).await.unwrap();
+        let later = repo.create_scheduled_message(chat.id, me, "later".to_string(), Utc::now() + Duration::hours(1)).await.unwrap();
+
+        let claimed = claimed_ids(&repo.claim_due_messages(1000).await.unwrap());
+        assert!(claimed.contains(&due.id));
+        assert!(!claimed.contains(&later.id));
+
+        assert!(!claimed_ids(&repo.claim_due_messages(1000).await.unwrap()).contains(&due.id));
+
+        // the scheduler which claimed it stopped before sending it
+        sqlx::query("UPDATE scheduled_messages SET claimed_at = now() - make_interval(secs => $2) WHERE id = $1")
+            .bind(due.id)
+            .bind((SCHEDULED_MESSAGE_CLAIM_SECONDS + 1) as f64)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let reclaimed = repo.claim_due_messages(1000).await.unwrap();
+        let reclaimed = reclaimed.iter().find(|m| m.id == due.id).unwrap();
+        assert_eq!(reclaimed.status, ScheduledMessageStatus::Sending);
+    }
+
+    #[tokio::test]
+    async fn send_claimed_message_should_send_it_once() {
+        let pool = connect().await;
+        let repo = ScheduleRepository::new(pool.clone());
+        let (me, friend) = (create_user(&pool).await, create_user(&pool).await);
+        let chat = ChatRepository::new(pool.clone(), false).create(me, vec![friend], "".to_string()).await.unwrap();
+
+        let due = repo.create_scheduled_message(chat.id, me, "due".to_string(), Utc::now() - Duration::seconds(1)).await.unwrap();
+        // claimed here, claim_due_messages would also take the due messages of the other test
+        sqlx::query("UPDATE scheduled_messages SET status = 'sending', claimed_at = now() WHERE id = $1")
+            .bind(due.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let message = OutgoingMessage {
+            chat_id: chat.id,
+            user_id: me,
+            r#type: MessageType::Text,
+            content: due.content.clone(),
+            thread_root_id: None,
+        };
+        let sent = repo.send_claimed_message(due.id, message.clone()).await.unwrap().unwrap();
+        assert!(repo.send_claimed_message(due.id, message).await.unwrap().is_none());
+
+        let sent_messages = repo.get_scheduled_messages(me, Some(chat.id), ScheduledMessageStatus::Sent).await.unwrap();
+        assert_eq!(sent_messages.len(), 1);
+        assert_eq!(sent_messages[0].message_id, Some(sent.id));
+    }
+}
@@ -0,0 +1,469 @@
+use std::collections::HashMap;
+use sqlx::{PgConnection, PgPool};
+use crate::error::AppError;
+use crate::models::{MessageSticker, Sticker, StickerPack, StickerPackRow, UserId};
+
+const MAX_CUSTOM_STICKERS: i64 = 200;
+const MAX_ADDED_STICKER_PACKS: i64 = 100;
+const CUSTOM_STICKER_PACK_NAME: &str = "Custom stickers";
+
+pub struct StickerRepository {
+    pub(crate) pool: PgPool,
+}
+
+impl StickerRepository {
+    pub(crate) fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+        }
+    }
+
+    /// My custom stickers first, then the server-managed packs, then the packs I added in the order I added them.
+    pub(crate) async fn get_sticker_packs(&self, user_id: UserId) -> Result<Vec<StickerPack>, AppError> {
+        let packs: Vec<StickerPackRow> = sqlx::query_as(
+            r#"
+            SELECT p.id, p.name, p.owner_id, p.created_at
+            FROM sticker_packs p
+            LEFT JOIN user_sticker_packs up ON up.pack_id = p.id AND up.user_id = $1
+            WHERE p.owner_id IS NULL OR p.owner_id = $1 OR up.user_id IS NOT NULL
+            ORDER BY p.owner_id IS NOT DISTINCT FROM $1 DESC, p.owner_id IS NOT NULL, up.created_at, p.id
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        self.with_stickers(packs).await
+    }
+
+    /// A pack I can see: server-managed, my own, added to my packs or sent in a chat I am a member of.
+    pub(crate) async fn get_sticker_pack(&self, user_id: UserId, pack_id: i64) -> Result<Option<StickerPack>, AppError> {
+        let pack: Option<StickerPackRow> = sqlx::query_as(
+            r#"
+            SELECT p.id, p.name, p.owner_id, p.created_at
+            FROM sticker_packs p
+            WHERE p.id = $2 AND (
+                p.owner_id IS NULL
+                OR p.owner_id = $1
+                OR EXISTS (SELECT 1 FROM user_sticker_packs up WHERE up.pack_id = p.id AND up.user_id = $1)
+                OR EXISTS (
+                    SELECT 1
+                    FROM stickers s
+                    JOIN message_stickers ms ON ms.sticker_id = s.id
+                    JOIN chat_members cm ON cm.chat_id = ms.chat_id
+                    WHERE s.pack_id = p.id AND cm.user_id = $1
+                )
+            )
+            "#,
+        )
+            .bind(user_id)
+            .bind(pack_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(self.with_stickers(pack.into_iter().collect()).await?.pop())
+    }
+
+    /// A sticker I can send: from a server-managed pack, my own pack or a pack I added.
+    pub(crate) async fn get_sticker(&self, user_id: UserId, sticker_id: i64) -> Result<Option<Sticker>, AppError> {
+        let sticker: Option<Sticker> = sqlx::query_as(
+            r#"
+            SELECT s.id, s.pack_id, s.image_url, s.emoji, s.created_at
+            FROM stickers s
+            JOIN sticker_packs p ON p.id = s.pack_id
+            LEFT JOIN user_sticker_packs up ON up.pack_id = p.id AND up.user_id = $1
+            WHERE s.id = $2 AND (p.owner_id IS NULL OR p.owner_id = $1 OR up.user_id IS NOT NULL)
+            "#,
+        )
+            .bind(user_id)
+            .bind(sticker_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(sticker)
+    }
+
+    /// Add the custom pack of another user, which I can see, to my sticker panel.
+    /// Server-managed packs and my own pack are always there, adding them again does nothing.
+    pub(crate) async fn add_sticker_pack(&self, user_id: UserId, pack_id: i64) -> Result<StickerPack, AppError> {
+        let pack = self.get_sticker_pack(user_id, pack_id).await?.ok_or(AppError::StickerNotFound)?;
+
+        if pack.owner_id.is_none() || pack.owner_id == Some(user_id) {
+            return Ok(pack);
+        }
+
+        let ret = sqlx::query(
+            r#"
+            INSERT INTO user_sticker_packs (user_id, pack_id)
+            SELECT $1, $2
+            WHERE (SELECT COUNT(*) FROM user_sticker_packs WHERE user_id = $1) < $3
+            ON CONFLICT (user_id, pack_id) DO NOTHING
+            "#,
+        )
+            .bind(user_id)
+            .bind(pack_id)
+            .bind(MAX_ADDED_STICKER_PACKS)
+            .execute(&self.pool)
+            .await?;
+
+        if ret.rows_affected() == 0 {
+            let added: Option<(i64,)> = sqlx::query_as("SELECT pack_id FROM user_sticker_packs WHERE user_id = $1 AND pack_id = $2")
+                .bind(user_id)
+                .bind(pack_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+            if added.is_none() {
+                return Err(AppError::InvalidInput(format!("Can not add more than {} sticker packs", MAX_ADDED_STICKER_PACKS)));
+            }
+        }
+
+        Ok(pack)
+    }
+
+    pub(crate) async fn remove_sticker_pack(&self, user_id: UserId, pack_id: i64) -> Result<bool, AppError> {
+        let ret = sqlx::query("DELETE FROM user_sticker_packs WHERE user_id = $1 AND pack_id = $2")
+            .bind(user_id)
+            .bind(pack_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(ret.rows_affected() > 0)
+    }
+
+    /// Add a sticker to my custom pack, which is created with the first sticker.
+    pub(crate) async fn add_custom_sticker(
+        &self,
+        user_id: UserId,
+        image_url: &str,
+        emoji: Option<&str>,
+    ) -> Result<Sticker, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        // the update locks the pack, so concurrent uploads can not go over the limit
+        let (pack_id,): (i64,) = sqlx::query_as(
+            r#"
+            INSERT INTO sticker_packs (name, owner_id)
+            VALUES ($1, $2)
+            ON CONFLICT (owner_id) DO UPDATE SET owner_id = EXCLUDED.owner_id
+            RETURNING id
+            "#,
+        )
+            .bind(CUSTOM_STICKER_PACK_NAME)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM stickers WHERE pack_id = $1")
+            .bind(pack_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        if count >= MAX_CUSTOM_STICKERS {
+            return Err(AppError::InvalidInput(format!("Can not have more than {} custom stickers", MAX_CUSTOM_STICKERS)));
+        }
+
+        let sticker = insert_sticker(&mut tx, pack_id, image_url, emoji).await?;
+
+        tx.commit().await?;
+
+        Ok(sticker)
+    }
+
+    pub(crate) async fn create_server_pack(&self, name: &str) -> Result<StickerPack, AppError> {
+        let pack: StickerPackRow = sqlx::query_as(
+            r#"
+            INSERT INTO sticker_packs (name)
+            VALUES ($1)
+            RETURNING id, name, owner_id, created_at
+            "#,
+        )
+            .bind(name)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(StickerPack {
+            id: pack.id,
+            name: pack.name,
+            owner_id: pack.owner_id,
+            created_at: pack.created_at,
+            stickers: vec![],
+        })
+    }
+
+    /// Add a sticker to a server-managed pack.
+    pub(crate) async fn add_server_sticker(
+        &self,
+        pack_id: i64,
+        image_url: &str,
+        emoji: Option<&str>,
+    ) -> Result<Sticker, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let pack: Option<(i64,)> = sqlx::query_as("SELECT id FROM sticker_packs WHERE id = $1 AND owner_id IS NULL FOR UPDATE")
+            .bind(pack_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        if pack.is_none() {
+            return Err(AppError::StickerNotFound);
+        }
+
+        let sticker = insert_sticker(&mut tx, pack_id, image_url, emoji).await?;
+
+        tx.commit().await?;
+
+        Ok(sticker)
+    }
+
+    pub(crate) async fn get_message_stickers(&self, keys: &[(i64, i64)]) -> Result<Vec<MessageSticker>, AppError> {
+        let (chat_ids, message_ids): (Vec<i64>, Vec<i64>) = keys.iter().cloned().unzip();
+
+        let stickers: Vec<MessageSticker> = sqlx::query_as(
+            r#"
+            SELECT ms.chat_id, ms.message_id, s.id, s.pack_id, s.image_url, s.emoji, s.created_at
+            FROM message_stickers ms
+            JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(chat_id, message_id)
+            ON ms.chat_id = k.chat_id AND ms.message_id = k.message_id
+            JOIN stickers s ON s.id = ms.sticker_id
+            "#,
+        )
+            .bind(chat_ids)
+            .bind(message_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(stickers)
+    }
+
+    async fn with_stickers(&self, packs: Vec<StickerPackRow>) -> Result<Vec<StickerPack>, AppError> {
+        let pack_ids: Vec<i64> = packs.iter().map(|p| p.id).collect();
+
+        let stickers: Vec<Sticker> = sqlx::query_as(
+            r#"
+            SELECT id, pack_id, image_url, emoji, created_at
+            FROM stickers
+            WHERE pack_id = ANY($1)
+            ORDER BY pack_id, id
+            "#,
+        )
+            .bind(pack_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut stickers_by_pack: HashMap<i64, Vec<Sticker>> = HashMap::new();
+        for sticker in stickers {
+            stickers_by_pack.entry(sticker.pack_id).or_default().push(sticker);
+        }
+
+        let packs = packs
+            .into_iter()
+            .map(|p| StickerPack {
+                stickers: stickers_by_pack.remove(&p.id).unwrap_or_default(),
+                id: p.id,
+                name: p.name,
+                owner_id: p.owner_id,
+                created_at: p.created_at,
+            })
+            .collect();
+
+        Ok(packs)
+    }
+}
+
+async fn insert_sticker(
+    conn: &mut PgConnection,
+    pack_id: i64,
+    image_url: &str,
+    emoji: Option<&str>,
+) -> Result<Sticker, AppError> {
+    let sticker: Sticker = sqlx::query_as(
+        r#"
+        INSERT INTO stickers (pack_id, image_url, emoji)
+        VALUES ($1, $2, $3)
+        RETURNING id, pack_id, image_url, emoji, created_at
+        "#,
+    )
+        .bind(pack_id)
+        .bind(image_url)
+        .bind(emoji)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(sticker)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::filter::OutgoingMessage;
+    use crate::models::{Message, MessageType, NewMessagePayload};
+    use crate::repository::{ChatRepository, MessageRepository};
+    use super::*;
+
+    const IMAGE_URL: &str = "https://files.ichat.local/stickers/1.png";
+
+    async fn connect() -> PgPool {
+        let config = AppConfig::load();
+
+        PgPool::connect(config.server.postgres_url.as_str())
+            .await
+            .unwrap()
+    }
+
+    async fn create_user(pool: &PgPool) -> UserId {
+        let (id,): (UserId,) = sqlx::query_as("INSERT INTO users (fullname, email, password_hash) VALUES ('sticker', $1, '') RETURNING id")
+            .bind(format!("sticker-{}@test.local", uuid::Uuid::now_v7()))
+            .fetch_one(pool)
+            .await
+            .unwrap();
+
+        id
+    }
+
+    /// A private chat of the two users in which `from` sends the sticker.
+    async fn send_sticker(pool: &PgPool, from: UserId, to: UserId, sticker_id: i64) -> Message {
+        let chat = ChatRepository::new(pool.clone(), false).create(from, vec![to], "".to_string()).await.unwrap();
+
+        let message = OutgoingMessage {
+            chat_id: chat.id,
+            user_id: from,
+            r#type: MessageType::Sticker,
+            content: "Sticker".to_string(),
+            thread_root_id: None,
+        };
+        let payload = NewMessagePayload::Sticker {
+            sticker_id,
+        };
+
+        let (message, _) = MessageRepository::new(pool.clone())
+            .create_message(message, Some(payload), &[], false)
+            .await
+            .unwrap();
+
+        message
+    }
+
+    #[tokio::test]
+    async fn get_sticker_packs_should_put_custom_then_server_then_added_packs() {
+        let pool = connect().await;
+        let repo = StickerRepository::new(pool.clone());
+        let (me, alice, bob) = (create_user(&pool).await, create_user(&pool).await, create_user(&pool).await);
+
+        let server_pack = repo.create_server_pack("Cats").await.unwrap();
+        let alice_sticker = repo.add_custom_sticker(alice, IMAGE_URL, None).await.unwrap();
+        let bob_sticker = repo.add_custom_sticker(bob, IMAGE_URL, None).await.unwrap();
+        let my_sticker = repo.add_custom_sticker(me, IMAGE_URL, Some("😀")).await.unwrap();
+
+        send_sticker(&pool, bob, me, bob_sticker.id).await;
+        send_sticker(&pool, alice, me, alice_sticker.id).await;
+        repo.add_sticker_pack(me, bob_sticker.pack_id).await.unwrap();
+        repo.add_sticker_pack(me, alice_sticker.pack_id).await.unwrap();
+
+        let expected = [my_sticker.pack_id, server_pack.id, bob_sticker.pack_id, alice_sticker.pack_id];
+        let packs: Vec<i64> = repo.get_sticker_packs(me).await.unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .filter(|id| expected.contains(id))
+            .collect();
+
+        assert_eq!(packs, expected);
+    }
+
+    #[tokio::test]
+    async fn add_sticker_pack_should_not_add_server_or_own_packs() {
+        let pool = connect().await;
+        let repo = StickerRepository::new(pool.clone());
+        let me = create_user(&pool).await;
+
+        let server_pack = repo.create_server_pack("Dogs").await.unwrap();
+        let my_sticker = repo.add_custom_sticker(me, IMAGE_URL, None).await.unwrap();
+
+        assert_eq!(repo.add_sticker_pack(me, server_pack.id).await.unwrap().id, server_pack.id);
+        assert_eq!(repo.add_sticker_pack(me, my_sticker.pack_id).await.unwrap().id, my_sticker.pack_id);
+
+        let (added,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user_sticker_packs WHERE user_id = $1")
+            .bind(me)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(added, 0);
+    }
+
+    #[tokio::test]
+    async fn custom_packs_should_only_be_visible_once_sent_to_me() {
+        let pool = connect().await;
+        let repo = StickerRepository::new(pool.clone());
+        let (me, alice) = (create_user(&pool).await, create_user(&pool).await);
+
+        let sticker = repo.add_custom_sticker(alice, IMAGE_URL, None).await.unwrap();
+
+        assert!(repo.get_sticker_pack(me, sticker.pack_id).await.unwrap().is_none());
+        assert!(matches!(repo.add_sticker_pack(me, sticker.pack_id).await, Err(AppError::StickerNotFound)));
+
+        send_sticker(&pool, alice, me, sticker.id).await;
+
+        assert!(repo.get_sticker_pack(me, sticker.pack_id).await.unwrap().is_some());
+        assert!(repo.get_sticker(me, sticker.id).await.unwrap().is_none());
+
+        repo.add_sticker_pack(me, sticker.pack_id).await.unwrap();
+
+        assert!(repo.get_sticker(me, sticker.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn add_custom_sticker_should_stop_at_the_limit() {
+        let pool = connect().await;
+        let repo = StickerRepository::new(pool.clone());
+        let me = create_user(&pool).await;
+
+        for _ in 0..MAX_CUSTOM_STICKERS {
+            repo.add_custom_sticker(me, IMAGE_URL, None).await.unwrap();
+        }
+
+        let ret = repo.add_custom_sticker(me, IMAGE_URL, None).await;
+        assert!(matches!(ret, Err(AppError::InvalidInput(_))), "{:?}", ret);
+    }
+
+    #[tokio::test]
+    async fn add_sticker_pack_should_stop_at_the_limit() {
+        let pool = connect().await;
+        let repo = StickerRepository::new(pool.clone());
+        let (me, alice) = (create_user(&pool).await, create_user(&pool).await);
+
+        // packs added before, they do not need to be visible to fill the panel
+        for _ in 0..MAX_ADDED_STICKER_PACKS {
+            let owner = create_user(&pool).await;
+            let sticker = repo.add_custom_sticker(owner, IMAGE_URL, None).await.unwrap();
+            sqlx::query("INSERT INTO user_sticker_packs (user_id, pack_id) VALUES ($1, $2)")
+                .bind(me)
+                .bind(sticker.pack_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let sticker = repo.add_custom_sticker(alice, IMAGE_URL, None).await.unwrap();
+        send_sticker(&pool, alice, me, sticker.id).await;
+
+        let ret = repo.add_sticker_pack(me, sticker.pack_id).await;
+        assert!(matches!(ret, Err(AppError::InvalidInput(_))), "{:?}", ret);
+    }
+
+    #[tokio::test]
+    async fn sticker_message_should_round_trip() {
+        let pool = connect().await;
+        let repo = StickerRepository::new(pool.clone());
+        let (me, alice) = (create_user(&pool).await, create_user(&pool).await);
+
+        let sticker = repo.add_custom_sticker(alice, IMAGE_URL, Some("🎉")).await.unwrap();
+        let message = send_sticker(&pool, alice, me, sticker.id).await;
+
+        assert_eq!(message.r#type, MessageType::Sticker);
+
+        let stickers = repo.get_message_stickers(&[(message.chat_id, message.id)]).await.unwrap();
+        assert_eq!(stickers.len(), 1);
+        assert_eq!((stickers[0].chat_id, stickers[0].message_id), (message.chat_id, message.id));
+        assert_eq!(stickers[0].sticker, sticker);
+    }
+}
@@ -9,10 +9,12 @@ use lettre::transport::smtp::authentication::Credentials;
 use r2d2::Pool;
 use r2d2_redis::RedisConnectionManager;
 use r2d2_redis::redis::Commands;
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use tracing::log::debug;
 use crate::error::AppError;
-use crate::models::{User, UserId};
+use crate::models::{PrivacySettings, User, UserId, UserRole, Visibility};
+use crate::utils::escape_like;
 
 pub struct UserRepository {
     biz: String,
@@ -30,6 +32,52 @@ impl UserRepository {
     }
 
     pub(crate) async fn send_email_code(&self, email: &str) -> Result<String, AppError> {
+        let key = format!("{}:{}:{}", self.biz, "email_code", email);
+        self.save_and_send_code(&key, email).await
+    }
+
+    pub(crate) async fn verify_email_code(&self, email: &str, code_input: &str) -> Result<bool, AppError> {
+        let key = format!("{}:{}:{}", self.biz, "email_code", email);
+        self.check_code(&key, code_input)
+    }
+
+    /// Send a code to the new email of a user, the code only works for this user.
+    pub(crate) async fn send_email_change_code(&self, user_id: UserId, email: &str) -> Result<String, AppError> {
+        if self.find_by_email(email).await?.is_some() {
+            return Err(AppError::EmailAlreadyExists(email.to_string()));
+        }
+
+        let key = format!("{}:{}:{}:{}", self.biz, "email_change_code", user_id, email);
+        self.save_and_send_code(&key, email).await
+    }
+
+    pub(crate) async fn change_email(&self, user_id: UserId, email: &str, code_input: &str) -> Result<User, AppError> {
+        let key = format!("{}:{}:{}:{}", self.biz, "email_change_code", user_id, email);
+        if !self.check_code(&key, code_input)? {
+            return Err(AppError::EmailCodeIncorrect);
+        }
+
+        let user: Option<User> = sqlx::query_as(
+            r#"
+            UPDATE users
+            SET email = $2
+            WHERE id = $1
+            RETURNING id, fullname, email, avatar, created_at, email_visibility, avatar_visibility, bio, status_text
+            "#,
+        )
+            .bind(user_id)
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(e) if e.is_unique_violation() => AppError::EmailAlreadyExists(email.to_string()),
+                e => AppError::SqlxError(e),
+            })?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+
+    async fn save_and_send_code(&self, key: &str, email: &str) -> Result<String, AppError> {
         // generate a random 6-digit code
         let code = rand::random::<u32>() % 1000000;
         // pad it to 6 digits
@@ -39,22 +87,21 @@ impl UserRepository {
 
         // save it in redis
         let mut rdb = self.rdb_pool.get()?;
-        rdb.set_ex(format!("{}:{}:{}", self.biz, "email_code", email), code.clone(), 600)?;
+        rdb.set_ex::<_, _, ()>(key, code.clone(), 600)?;
 
         send_email_code(email, &code.to_string()).await?;
 
         Ok(code)
     }
 
-    pub(crate) async fn verify_email_code(&self, email: &str, code_input: &str) -> Result<bool, AppError> {
+    fn check_code(&self, key: &str, code_input: &str) -> Result<bool, AppError> {
         let mut rdb = self.rdb_pool.get()?;
-        let key = format!("{}:{}:{}", self.biz, "email_code", email);
-        let code: Option<String> = rdb.get(key.clone())?;
+        let code: Option<String> = rdb.get(key)?;
 
         match code {
             Some(c) => {
                 if c == code_input {
-                    rdb.del(key)?;
+                    rdb.del::<_, ()>(key)?;
                     Ok(true)
                 } else {
                     Ok(false)
@@ -64,10 +111,41 @@ impl UserRepository {
         }
     }
 
+    /// Update the profile, fields given as `None` are left unchanged and empty strings clear them.
+    pub(crate) async fn update_profile(
+        &self,
+        user_id: UserId,
+        fullname: Option<String>,
+        avatar: Option<String>,
+        bio: Option<String>,
+        status_text: Option<String>,
+    ) -> Result<User, AppError> {
+        let user: Option<User> = sqlx::query_as(
+            r#"
+            UPDATE users
+            SET fullname = COALESCE($2, fullname),
+                avatar = NULLIF(COALESCE($3, avatar), ''),
+                bio = NULLIF(COALESCE($4, bio), ''),
+                status_text = NULLIF(COALESCE($5, status_text), '')
+            WHERE id = $1
+            RETURNING id, fullname, email, avatar, created_at, email_visibility, avatar_visibility, bio, status_text
+            "#,
+        )
+            .bind(user_id)
+            .bind(fullname)
+            .bind(avatar)
+            .bind(bio)
+            .bind(status_text)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+
     pub(crate) async fn find_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
         let user: Option<User> = sqlx::query_as(
             r#"
-            SELECT id, fullname, email, created_at FROM users WHERE email = $1
+            SELECT id, fullname, email, avatar, created_at, email_visibility, avatar_visibility, bio, status_text FROM users WHERE email = $1
             "#,
         )
             .bind(email)
@@ -80,7 +158,7 @@ impl UserRepository {
     pub(crate) async fn find_by_id(&self, id: UserId) -> Result<Option<User>, AppError> {
         let user: Option<User> = sqlx::query_as(
             r#"
-            SELECT id, fullname, email, avatar, created_at FROM users WHERE id = $1
+            SELECT id, fullname, email, avatar, created_at, email_visibility, avatar_visibility, bio, status_text FROM users WHERE id = $1
             "#,
         )
             .bind(id)
@@ -90,6 +168,74 @@ impl UserRepository {
         Ok(user)
     }
 
+    /// A user the viewer could find in the user search: not deleted, and neither of them blocked the other.
+    pub(crate) async fn find_visible_user(&self, viewer_id: UserId, id: UserId) -> Result<Option<User>, AppError> {
+        let user: Option<User> = sqlx::query_as(
+            r#"
+            SELECT id, fullname, email, avatar, created_at, email_visibility, avatar_visibility, bio, status_text
+            FROM users
+            WHERE id = $2 AND deleted_at IS NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM user_blocks
+                WHERE (user_id = $1 AND blocked_id = $2) OR (user_id = $2 AND blocked_id = $1)
+            )
+            "#,
+        )
+            .bind(viewer_id)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(user)
+    }
+
+    /// Fails with `AccountBanned` or `AccountSuspended` if the user is locked by an operator,
+    /// and with `Unauthorized` if the account was deleted, so its tokens stop working.
+    pub(crate) async fn check_restriction(&self, id: UserId) -> Result<(), AppError> {
+        let restriction: Option<(bool, Option<DateTime<Utc>>, Option<String>, Option<DateTime<Utc>>)> = sqlx::query_as(
+            r#"
+            SELECT banned, suspended_until, suspension_reason, deleted_at FROM users WHERE id = $1
+            "#,
+        )
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match restriction {
+            None => Err(AppError::UserNotFound),
+            Some((_, _, _, Some(_))) => Err(AppError::Unauthorized),
+            Some((true, _, reason, _)) => Err(AppError::AccountBanned { reason }),
+            Some((false, Some(until), reason, _)) if until > Utc::now() => Err(AppError::AccountSuspended { until, reason }),
+            Some(_) => Ok(()),
+        }
+    }
+
+    pub(crate) async fn get_role(&self, id: UserId) -> Result<Option<UserRole>, AppError> {
+        let role: Option<(UserRole,)> = sqlx::query_as(
+            r#"
+            SELECT role FROM users WHERE id = $1 AND deleted_at IS NULL
+            "#,
+        )
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(role.map(|(r,)| r))
+    }
+
+    pub(crate) async fn find_by_ids(&self, ids: &[UserId]) -> Result<Vec<User>, AppError> {
+        let users: Vec<User> = sqlx::query_as(
+            r#"
+            SELECT id, fullname, email, avatar, created_at, email_visibility, avatar_visibility, bio, status_text FROM users WHERE id = ANY($1)
+            "#,
+        )
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(users)
+    }
+
     pub(crate) async fn create(&self, email: &str, password: &str, fullname: &str) -> Result<User, AppError> {
         let user = self.find_by_email(email).await?;
 
@@ -115,22 +261,138 @@ impl UserRepository {
         Ok(user)
     }
 
-    pub(crate) async fn get_all_users(&self) -> Result<Vec<User>, AppError> {
+    /// Search users by fullname prefix, or by email prefix if they allow it. Sorted by id.
+    /// Users who blocked the searcher or were blocked by them are left out.
+    pub(crate) async fn search_users(
+        &self,
+        user_id: UserId,
+        query: &str,
+        after_id: Option<UserId>,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
         let users: Vec<User> = sqlx::query_as(
             r#"
-            SELECT id, fullname, email, avatar, created_at FROM users
+            SELECT id, fullname, email, avatar, created_at, email_visibility, avatar_visibility, bio, status_text
+            FROM users
+            WHERE id != $1 AND deleted_at IS NULL
+            AND (
+                lower(fullname) LIKE lower($2) || '%'
+                OR (email_discoverable AND lower(email) LIKE lower($2) || '%')
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM user_blocks
+                WHERE (user_id = $1 AND blocked_id = users.id) OR (user_id = users.id AND blocked_id = $1)
+            )
+            AND id > $3
+            ORDER BY id
+            LIMIT $4
             "#,
         )
+            .bind(user_id)
+            .bind(escape_like(query))
+            .bind(after_id.unwrap_or_default())
+            .bind(limit)
             .fetch_all(&self.pool)
             .await?;
 
         Ok(users)
     }
 
+    /// Returns the (viewer id, user id) pairs of the keys in which the users are contacts or share a chat.
+    pub(crate) async fn get_known_users(&self, keys: &[(UserId, UserId)]) -> Result<Vec<(UserId, UserId)>, AppError> {
+        let (viewer_ids, user_ids): (Vec<UserId>, Vec<UserId>) = keys.iter().cloned().unzip();
+
+        let known: Vec<(UserId, UserId)> = sqlx::query_as(
+            r#"
+            SELECT k.viewer_id, k.user_id
+            FROM UNNEST($1::BIGINT[], $2::BIGINT[]) AS k(viewer_id, user_id)
+            WHERE EXISTS (
+                SELECT 1
+                FROM contacts c
+                WHERE c.user_id = k.viewer_id AND c.contact_id = k.user_id
+            ) OR EXISTS (
+                SELECT 1
+                FROM chat_members a
+                JOIN chat_members b ON a.chat_id = b.chat_id
+                WHERE a.user_id = k.viewer_id AND b.user_id = k.user_id
+            )
+            "#,
+        )
+            .bind(viewer_ids)
+            .bind(user_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(known)
+    }
+
+    /// Ids of the contacts of the user and of everyone sharing a chat with them.
+    pub(crate) async fn get_known_user_ids(&self, user_id: UserId) -> Result<Vec<UserId>, AppError> {
+        let ids: Vec<(UserId,)> = sqlx::query_as(
+            r#"
+            SELECT contact_id FROM contacts WHERE user_id = $1
+            UNION
+            SELECT b.user_id
+            FROM chat_members a
+            JOIN chat_members b ON a.chat_id = b.chat_id
+            WHERE a.user_id = $1
+            "#,
+        )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(ids.into_iter().map(|(id,)| id).collect())
+    }
+
+    pub(crate) async fn get_privacy_settings(&self, user_id: UserId) -> Result<PrivacySettings, AppError> {
+        let settings: Option<PrivacySettings> = sqlx::query_as(
+            r#"
+            SELECT email_discoverable, email_visibility, avatar_visibility, forward_attribution FROM users WHERE id = $1
+            "#,
+        )
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        settings.ok_or(AppError::UserNotFound)
+    }
+
+    /// Update the privacy settings, fields given as `None` are left unchanged.
+    pub(crate) async fn update_privacy_settings(
+        &self,
+        user_id: UserId,
+        email_discoverable: Option<bool>,
+        email_visibility: Option<Visibility>,
+        avatar_visibility: Option<Visibility>,
+        forward_attribution: Option<bool>,
+    ) -> Result<PrivacySettings, AppError> {
+        let settings: Option<PrivacySettings> = sqlx::query_as(
+            r#"
+            UPDATE users
+            SET email_discoverable = COALESCE($2, email_discoverable),
+                email_visibility = COALESCE($3, email_visibility),
+                avatar_visibility = COALESCE($4, avatar_visibility),
+                forward_attribution = COALESCE($5, forward_attribution)
+            WHERE id = $1
+            RETURNING email_discoverable, email_visibility, avatar_visibility, forward_attribution
+            "#,
+        )
+            .bind(user_id)
+            .bind(email_discoverable)
+            .bind(email_visibility)
+            .bind(avatar_visibility)
+            .bind(forward_attribution)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        settings.ok_or(AppError::UserNotFound)
+    }
+
     pub(crate) async fn verify_password(&self, email: &str, password: &str) -> Result<User, AppError> {
         let user: Option<User> = sqlx::query_as(
             r#"
-            SELECT id, fullname, email, password_hash, avatar, created_at FROM users WHERE email = $1
+            SELECT id, fullname, email, password_hash, avatar, created_at, email_visibility, avatar_visibility, bio, status_text FROM users WHERE email = $1
             "#,
         )
             .bind(email)
@@ -144,6 +406,7 @@ impl UserRepository {
                 let is_valid = verify_password(password, &password_hash.unwrap_or_default())?;
 
                 if is_valid {
+                    self.check_restriction(user.id).await?;
                     Ok(user)
                 } else {
                     Err(AppError::PasswordError)
@@ -202,6 +465,7 @@ async fn send_email_code(email: &str, code: &str) -> Result<(), AppError> {
 #[cfg(test)]
 mod tests {
     use crate::config::AppConfig;
+    use crate::repository::AdminRepository;
     use super::*;
 
     #[tokio::test]
@@ -329,4 +593,87 @@ mod tests {
         let user = repo.verify_password("863461783@qq.com", "123789").await;
         assert!(user.is_err());
     }
+
+    /// A user repository on the unit test database, `check_restriction` does not need redis.
+    async fn restriction_repo() -> (UserRepository, AdminRepository, PgPool) {
+        let config = AppConfig::load();
+
+        let pool = PgPool::connect(config.server.postgres_url.as_str())
+            .await
+            .unwrap();
+
+        let redis_manager = RedisConnectionManager::new(config.server.redis_url.as_str())
+            .expect("Failed to create redis connection manager");
+
+        let rdb_pool = Pool::builder().max_size(1).build_unchecked(redis_manager);
+
+        (UserRepository::new(pool.clone(), rdb_pool), AdminRepository::new(pool.clone()), pool)
+    }
+
+    async fn create_test_user(pool: &PgPool) -> UserId {
+        let (id,): (UserId,) = sqlx::query_as("INSERT INTO users (fullname, email, password_hash) VALUES ('restricted', $1, '') RETURNING id")
+            .bind(format!("restricted-{}@test.local", uuid::Uuid::now_v7()))
+            .fetch_one(pool)
+            .await
+            .unwrap();
+
+        id
+    }
+
+    #[tokio::test]
+    async fn check_restriction_should_reject_banned_users() {
+        let (repo, admin_repo, pool) = restriction_repo().await;
+        let user_id = create_test_user(&pool).await;
+
+        repo.check_restriction(user_id).await.unwrap();
+
+        admin_repo.ban_user(user_id, "spam").await.unwrap();
+        let ret = repo.check_restriction(user_id).await;
+        assert!(matches!(&ret, Err(AppError::AccountBanned { reason: Some(r) }) if r == "spam"), "{:?}", ret);
+
+        admin_repo.unban_user(user_id).await.unwrap();
+        repo.check_restriction(user_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn check_restriction_should_reject_suspended_users_until_the_end() {
+        let (repo, admin_repo, pool) = restriction_repo().await;
+        let user_id = create_test_user(&pool).await;
+
+        let until = Utc::now() + chrono::Duration::hours(1);
+        admin_repo.suspend_user(user_id, Some(until), "flood").await.unwrap();
+        let ret = repo.check_restriction(user_id).await;
+        assert!(matches!(&ret, Err(AppError::AccountSuspended { reason: Some(r), .. }) if r == "flood"), "{:?}", ret);
+
+        // a suspension which ended no longer restricts the user
+        admin_repo.suspend_user(user_id, Some(Utc::now() - chrono::Duration::seconds(1)), "flood").await.unwrap();
+        repo.check_restriction(user_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn check_restriction_should_reject_indefinitely_suspended_users() {
+        let (repo, admin_repo, pool) = restriction_repo().await;
+        let user_id = create_test_user(&pool).await;
+
+        admin_repo.suspend_user(user_id, None, "abuse").await.unwrap();
+        let ret = repo.check_restriction(user_id).await;
+        assert!(matches!(&ret, Err(AppError::AccountSuspended { until, .. }) if *until > Utc::now() + chrono::Duration::days(365 * 1000)), "{:?}", ret);
+
+        admin_repo.unsuspend_user(user_id).await.unwrap();
+        repo.check_restriction(user_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn check_restriction_should_reject_deleted_users() {
+        let (repo, _, pool) = restriction_repo().await;
+        let user_id = create_test_user(&pool).await;
+
+        sqlx::query("UPDATE users SET deleted_at = now() WHERE id = $1")
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(matches!(repo.check_restriction(user_id).await, Err(AppError::Unauthorized)));
+    }
 }
@@ -6,7 +6,7 @@ use tokio::sync::broadcast;
 use tracing::debug;
 use crate::app_state::AppState;
 use crate::error::AppError;
-use crate::models::{Chat, Message, UserId};
+use crate::models::{Chat, Message, SessionId, UserId};
 use crate::notification::{AppEvent, Notification};
 
 pub struct SubscriptionRoot;
@@ -59,15 +59,29 @@ impl SubscriptionRoot {
             .map_err(|_| AppError::GetGraphqlUserIdError)?;
 
         let mut rv = state.sender.subscribe();
+        let mut blocked = load_blocked(state, *user_id).await?;
 
         Ok(async_stream::stream! {
             loop {
                 let noti = rv.recv().await;
                 match noti {
                     Ok(noti) => {
+                        if update_blocked(&mut blocked, *user_id, &noti.event) {
+                            continue;
+                        }
+
+                        if let AppEvent::MessagesExpired(expired) = &noti.event {
+                            if expired.member_ids.contains(user_id) {
+                                yield noti.event;
+                            }
+                            continue;
+                        }
+
                         let message: Option<Message> = None;
                         let message = match noti.event.clone() {
                             AppEvent::NewMessage(new_message) => Some(new_message),
+                            AppEvent::MessageDeleted(deleted) => Some(deleted.data),
+                            AppEvent::MessageUpdated(updated) => Some(updated.data),
                             _ => None
                         };
 
@@ -79,7 +93,7 @@ impl SubscriptionRoot {
 
                                 if let Ok(members) = members {
                                     let member_ids: HashSet<i64> = members.iter().map(|u| u.id).collect();
-                                    if member_ids.contains(&user_id) {
+                                    if member_ids.contains(&user_id) && !blocked.contains(&message.user_id) {
                                         yield noti.event;
                                     }
                                 }
@@ -100,21 +114,194 @@ impl SubscriptionRoot {
             .data::<UserId>()
             .map_err(|_| AppError::GetGraphqlUserIdError)?;
 
+        // the events below are only matched by chat id, so the subscriber must be a member
+        if state.chat_repo.get_chat_by_id(chat_id, *user_id).await.is_err() {
+            return Err(AppError::ChatNotFound);
+        }
+
         let mut rv = state.sender.subscribe();
+        let mut blocked = load_blocked(state, *user_id).await?;
 
         Ok(async_stream::stream! {
             loop {
                 let noti = rv.recv().await;
                 match noti {
                     Ok(noti) => {
+                        if update_blocked(&mut blocked, *user_id, &noti.event) {
+                            continue;
+                        }
+
+                        let event_chat_id = match &noti.event {
+                            AppEvent::MessagePinned(pinned) if !blocked.contains(&pinned.data.message.user_id) => Some(pinned.data.message.chat_id),
+                            AppEvent::MessageUnpinned(unpinned) => Some(unpinned.chat_id),
+                            AppEvent::ThreadUpdated(updated) if !blocked.contains(&updated.reply.user_id) => Some(updated.data.chat_id),
+                            AppEvent::MessagesExpired(expired) => Some(expired.chat_id),
+                            _ => None,
+                        };
+
+                        if event_chat_id == Some(chat_id) {
+                            yield noti.event;
+                            continue;
+                        }
+
                         let message: Option<Message> = None;
                         let message = match noti.event.clone() {
                             AppEvent::NewMessage(new_message) => Some(new_message),
+                            AppEvent::MessageDeleted(deleted) => Some(deleted.data),
+                            AppEvent::MessageUpdated(updated) => Some(updated.data),
                             _ => None
                         };
 
                         if let Some(message) = message {
-                            if message.chat_id == chat_id {
+                            if message.chat_id == chat_id && !blocked.contains(&message.user_id) {
+                                yield noti.event;
+                            }
+                        }
+                    },
+                    Err(e) => {
+                        debug!("Error: {:?}", e);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Friend requests sent to me, my friend requests accepted by others, and users I blocked or unblocked.
+    async fn contact<'a>(&self, ctx: &'a Context<'a>) -> Result<impl Stream<Item = AppEvent> + 'a, AppError> {
+        let user_id = ctx
+            .data::<UserId>()
+            .map_err(|_| AppError::GetGraphqlUserIdError)?;
+        let state = ctx.data_unchecked::<AppState>();
+
+        let mut rv = state.sender.subscribe();
+
+        Ok(async_stream::stream! {
+            loop {
+                let noti = rv.recv().await;
+                match noti {
+                    Ok(noti) => {
+                        let is_mine = match &noti.event {
+                            AppEvent::FriendRequestReceived(received) => received.data.to_user_id == *user_id,
+                            AppEvent::FriendRequestAccepted(accepted) => accepted.data.from_user_id == *user_id,
+                            AppEvent::BlockChanged(changed) => changed.user_id == *user_id,
+                            _ => false,
+                        };
+
+                        if is_mine {
+                            yield noti.event;
+                        }
+                    },
+                    Err(e) => {
+                        debug!("Error: {:?}", e);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Profile changes of the users sharing a chat with me, or in my contacts.
+    async fn user_profile<'a>(&self, ctx: &'a Context<'a>) -> Result<impl Stream<Item = AppEvent> + 'a, AppError> {
+        let user_id = ctx
+            .data::<UserId>()
+            .map_err(|_| AppError::GetGraphqlUserIdError)?;
+        let state = ctx.data_unchecked::<AppState>();
+
+        let mut rv = state.sender.subscribe();
+        // loaded again on the first profile change after chats or contacts may have changed
+        let mut known: Option<HashSet<UserId>> = None;
+
+        Ok(async_stream::stream! {
+            loop {
+                let noti = rv.recv().await;
+                match noti {
+                    Ok(noti) => {
+                        match &noti.event {
+                            AppEvent::CreatedChat(_) | AppEvent::ChatDeleted(_) => known = None,
+                            AppEvent::FriendRequestAccepted(accepted)
+                                if accepted.data.from_user_id == *user_id || accepted.data.to_user_id == *user_id => known = None,
+                            _ => {}
+                        }
+
+                        if let AppEvent::UserProfileChanged(changed) = &noti.event {
+                            let changed_id = changed.data.id;
+                            if known.is_none() {
+                                match state.user_repo.get_known_user_ids(*user_id).await {
+                                    Ok(ids) => known = Some(ids.into_iter().collect()),
+                                    Err(e) => {
+                                        debug!("Error: {:?}", e);
+                                        continue;
+                                    }
+                                }
+                            }
+
+                            if changed_id == *user_id || known.as_ref().is_some_and(|known| known.contains(&changed_id)) {
+                                yield noti.event;
+                            }
+                        }
+                    },
+                    Err(e) => {
+                        debug!("Error: {:?}", e);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Messages mentioning me in any of my chats.
+    async fn mention<'a>(&self, ctx: &'a Context<'a>) -> Result<impl Stream<Item = AppEvent> + 'a, AppError> {
+        let user_id = ctx
+            .data::<UserId>()
+            .map_err(|_| AppError::GetGraphqlUserIdError)?;
+        let state = ctx.data_unchecked::<AppState>();
+
+        let mut rv = state.sender.subscribe();
+        let mut blocked = load_blocked(state, *user_id).await?;
+
+        Ok(async_stream::stream! {
+            loop {
+                let noti = rv.recv().await;
+                match noti {
+                    Ok(noti) => {
+                        if update_blocked(&mut blocked, *user_id, &noti.event) {
+                            continue;
+                        }
+
+                        if let AppEvent::Mentioned(mentioned) = &noti.event {
+                            let message = &mentioned.data;
+                            if message.user_id == *user_id {
+                                continue;
+                            }
+
+                            if mentioned.user_ids.contains(user_id) && !blocked.contains(&message.user_id) {
+                                yield noti.event;
+                            }
+                        }
+                    },
+                    Err(e) => {
+                        debug!("Error: {:?}", e);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Draft changes made by my other sessions.
+    async fn draft<'a>(&self, ctx: &'a Context<'a>) -> Result<impl Stream<Item = AppEvent> + 'a, AppError> {
+        let user_id = ctx
+            .data::<UserId>()
+            .map_err(|_| AppError::GetGraphqlUserIdError)?;
+        let session_id = ctx.data::<SessionId>().ok().map(|s| s.0.clone());
+        let state = ctx.data_unchecked::<AppState>();
+
+        let mut rv = state.sender.subscribe();
+
+        Ok(async_stream::stream! {
+            loop {
+                let noti = rv.recv().await;
+                match noti {
+                    Ok(noti) => {
+                        if let AppEvent::DraftUpdated(updated) = &noti.event {
+                            if updated.user_id == *user_id && (updated.session_id.is_none() || updated.session_id != session_id) {
                                 yield noti.event;
                             }
                         }
@@ -140,11 +327,19 @@ impl SubscriptionRoot {
                 let noti = rv.recv().await;
                 match noti {
                     Ok(noti) => {
+                        if let AppEvent::PollUpdated(updated) = &noti.event {
+                            if updated.member_ids.contains(user_id) {
+                                yield noti.event;
+                            }
+                            continue;
+                        }
+
                         let chat: Option<Chat> = None;
                         let chat = match noti.event.clone() {
                             AppEvent::CreatedChat(created_chat) => Some(created_chat.data),
                             AppEvent::ChatOwnerChanged(chat_owner_changed) => Some(chat_owner_changed.data),
                             AppEvent::ChatNameChanged(chat_name_changed) => Some(chat_name_changed.data),
+                            AppEvent::ChatMessageTtlChanged(chat_message_ttl_changed) => Some(chat_message_ttl_changed.data),
                             AppEvent::ChatDeleted(chat_deleted) => Some(chat_deleted.data),
                             _ => None,
                         };
@@ -161,3 +356,27 @@ impl SubscriptionRoot {
         })
     }
 }
+
+/// Users the subscriber blocked, loaded once per subscription and kept up to date by `update_blocked`.
+async fn load_blocked(state: &AppState, user_id: UserId) -> Result<HashSet<UserId>, AppError> {
+    let blocked = state.contact_repo.get_blocked_ids(user_id).await?;
+
+    Ok(blocked.into_iter().collect())
+}
+
+/// Apply a block change of the subscriber, returns whether the event was a block change.
+fn update_blocked(blocked: &mut HashSet<UserId>, user_id: UserId, event: &AppEvent) -> bool {
+    let AppEvent::BlockChanged(changed) = event else {
+        return false;
+    };
+
+    if changed.user_id == user_id {
+        if changed.blocked {
+            blocked.insert(changed.blocked_id);
+        } else {
+            blocked.remove(&changed.blocked_id);
+        }
+    }
+
+    true
+}
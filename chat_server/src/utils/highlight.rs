@@ -0,0 +1,122 @@
+pub(crate) const HIGHLIGHT_START: &str = "<mark>";
+pub(crate) const HIGHLIGHT_STOP: &str = "</mark>";
+/// Markers `ts_headline` puts around matches, turned into highlight tags after the text is escaped.
+pub(crate) const HEADLINE_START: &str = "\u{2}";
+pub(crate) const HEADLINE_STOP: &str = "\u{3}";
+
+/// Build a snippet of `content` around the first case-insensitive match of `query`,
+/// with the match wrapped in highlight tags and at most `radius` chars on each side.
+/// The content is HTML-escaped, only the highlight tags are markup.
+/// Works on chars, so CJK text which full-text search can't split is highlighted as well.
+pub(crate) fn highlight_snippet(content: &str, query: &str, radius: usize) -> Option<String> {
+    let query: Vec<char> = query.trim().chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return None;
+    }
+
+    let chars: Vec<char> = content.chars().collect();
+    let lowered: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    let start = lowered
+        .windows(query.len())
+        .position(|w| w == query.as_slice())?;
+    let end = start + query.len();
+
+    let from = start.saturating_sub(radius);
+    let to = (end + radius).min(chars.len());
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    snippet.push_str(&escape_html(&chars[from..start].iter().collect::<String>()));
+    snippet.push_str(HIGHLIGHT_START);
+    snippet.push_str(&escape_html(&chars[start..end].iter().collect::<String>()));
+    snippet.push_str(HIGHLIGHT_STOP);
+    snippet.push_str(&escape_html(&chars[end..to].iter().collect::<String>()));
+    if to < chars.len() {
+        snippet.push('…');
+    }
+
+    Some(snippet)
+}
+
+/// Escape a headline of `ts_headline` built with the headline markers, then turn the markers into highlight tags.
+pub(crate) fn render_headline(headline: &str) -> String {
+    escape_html(headline)
+        .replace(HEADLINE_START, HIGHLIGHT_START)
+        .replace(HEADLINE_STOP, HIGHLIGHT_STOP)
+}
+
+pub(crate) fn escape_html(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape `%`, `_` and `\` so the input is matched literally by `LIKE`.
+pub(crate) fn escape_like(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_snippet_should_work() {
+        let snippet = highlight_snippet("Hello World", "world", 10).unwrap();
+        assert_eq!(snippet, "Hello <mark>World</mark>");
+    }
+
+    #[test]
+    fn highlight_snippet_should_work_with_cjk() {
+        let snippet = highlight_snippet("今天我们一起去吃火锅吧", "火锅", 2).unwrap();
+        assert_eq!(snippet, "…去吃<mark>火锅</mark>吧");
+    }
+
+    #[test]
+    fn highlight_snippet_without_match_should_be_none() {
+        assert!(highlight_snippet("Hello World", "rust", 10).is_none());
+        assert!(highlight_snippet("Hello World", "  ", 10).is_none());
+    }
+
+    #[test]
+    fn highlight_snippet_should_escape_html() {
+        let snippet = highlight_snippet("<script>alert('x')</script> hello & bye", "hello", 40).unwrap();
+        assert_eq!(snippet, "&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; <mark>hello</mark> &amp; bye");
+
+        let snippet = highlight_snippet("<script>alert(1)</script>", "script>", 40).unwrap();
+        assert_eq!(snippet, "&lt;<mark>script&gt;</mark>alert(1)&lt;/script&gt;");
+    }
+
+    #[test]
+    fn render_headline_should_escape_html() {
+        let headline = format!("<script>{}alert{}(1)</script>", HEADLINE_START, HEADLINE_STOP);
+        assert_eq!(render_headline(&headline), "&lt;script&gt;<mark>alert</mark>(1)&lt;/script&gt;");
+    }
+
+    #[test]
+    fn escape_like_should_work() {
+        assert_eq!(escape_like("100%_a\\b"), "100\\%\\_a\\\\b");
+    }
+}
@@ -1,3 +1,7 @@
 mod jwt;
+mod highlight;
+mod storage;
 
 pub(crate) use jwt::*;
+pub(crate) use highlight::*;
+pub(crate) use storage::*;
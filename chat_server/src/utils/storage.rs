@@ -0,0 +1,51 @@
+use url::Url;
+use crate::config::StorageConfig;
+
+/// Whether `url` points to a file uploaded to the storage, so clients never load files from other hosts.
+pub(crate) fn is_storage_url(url: &str, config: &StorageConfig) -> bool {
+    let (Ok(url), Ok(base)) = (Url::parse(url), Url::parse(&config.public_url)) else {
+        return false;
+    };
+
+    url.origin() == base.origin()
+        && url.username().is_empty()
+        && url.password().is_none()
+        && url.path().starts_with(base.path())
+        && url.path().len() > base.path().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> StorageConfig {
+        StorageConfig {
+            public_url: "https://files.ichat.local/uploads/".to_string(),
+        }
+    }
+
+    #[test]
+    fn is_storage_url_should_accept_uploaded_files() {
+        assert!(is_storage_url("https://files.ichat.local/uploads/a/b.png", &config()));
+        assert!(is_storage_url("https://FILES.ichat.local:443/uploads/b.png", &config()));
+    }
+
+    #[test]
+    fn is_storage_url_should_reject_other_urls() {
+        let config = config();
+        assert!(!is_storage_url("http://files.ichat.local/uploads/b.png", &config));
+        assert!(!is_storage_url("https://files.ichat.local:8443/uploads/b.png", &config));
+        assert!(!is_storage_url("https://evil.example/uploads/b.png", &config));
+        assert!(!is_storage_url("https://files.ichat.local.evil.example/uploads/b.png", &config));
+        assert!(!is_storage_url("https://user@files.ichat.local/uploads/b.png", &config));
+        assert!(!is_storage_url("https://files.ichat.local/uploads/../private/b.png", &config));
+        assert!(!is_storage_url("https://files.ichat.local/uploads/", &config));
+        assert!(!is_storage_url("not a url", &config));
+    }
+
+    #[test]
+    fn is_storage_url_without_public_url_should_reject_everything() {
+        let config = StorageConfig::default();
+        assert!(!is_storage_url("https://files.ichat.local/uploads/b.png", &config));
+    }
+}
@@ -0,0 +1,8 @@
+-- Per-member chat settings
+ALTER TABLE chat_members
+    ADD COLUMN muted_until TIMESTAMPTZ,
+    ADD COLUMN pinned_order INT,
+    ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN nickname VARCHAR(64);
+
+CREATE INDEX IF NOT EXISTS chat_members_user_id_idx ON chat_members (user_id, archived);
@@ -0,0 +1,32 @@
+-- Track the last activity of a chat, so the chat list can be sorted and paginated
+ALTER TABLE chats ADD COLUMN last_activity_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+UPDATE chats c
+    SET last_activity_at = COALESCE(
+        (SELECT MAX(m.created_at) FROM messages m WHERE m.chat_id = c.id),
+        c.created_at,
+        CURRENT_TIMESTAMP
+    );
+
+CREATE INDEX IF NOT EXISTS chats_last_activity_at_idx ON chats (last_activity_at DESC, id DESC);
+
+-- only notify chat changes that clients care about, not every last activity bump
+DROP TRIGGER IF EXISTS chat_change_trigger ON chats;
+
+CREATE TRIGGER chat_change_trigger
+    AFTER INSERT OR UPDATE OF name, owner_id OR DELETE
+    ON chats
+    FOR EACH ROW
+    EXECUTE FUNCTION notify_chat_change();
+
+CREATE OR REPLACE FUNCTION notify_message()
+    RETURNS TRIGGER
+    AS $$
+BEGIN
+    PERFORM increase_unread_count(NEW.chat_id, NEW.user_id);
+    UPDATE chats SET last_activity_at = COALESCE(NEW.created_at, now()) WHERE id = NEW.chat_id;
+    PERFORM pg_notify('new_message', row_to_json(NEW)::text);
+    RETURN NEW;
+END;
+    $$
+LANGUAGE plpgsql;
@@ -0,0 +1,14 @@
+-- Full-text search on messages
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- 'simple' config does not stem, it works the same for every language the users write in
+ALTER TABLE messages ADD COLUMN search_vector TSVECTOR
+    GENERATED ALWAYS AS (
+        CASE WHEN type = 'text' THEN to_tsvector('simple'::regconfig, content) ELSE NULL END
+    ) STORED;
+
+-- indexes on the partitioned table are created on every partition, including the ones created later
+CREATE INDEX IF NOT EXISTS messages_search_vector_idx ON messages USING GIN (search_vector);
+
+-- tsvector can't split CJK text into words, trigram index backs the substring match for it
+CREATE INDEX IF NOT EXISTS messages_content_trgm_idx ON messages USING GIN (content gin_trgm_ops);
@@ -0,0 +1,11 @@
+-- Who can see a profile field: everyone, people I share a chat with, or nobody
+CREATE TYPE visibility AS ENUM ('everyone', 'contacts', 'nobody');
+
+ALTER TABLE users
+    ADD COLUMN email_discoverable BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN email_visibility visibility NOT NULL DEFAULT 'contacts',
+    ADD COLUMN avatar_visibility visibility NOT NULL DEFAULT 'everyone';
+
+-- prefix search on fullname and email
+CREATE INDEX IF NOT EXISTS users_fullname_prefix_idx ON users (lower(fullname) text_pattern_ops);
+CREATE INDEX IF NOT EXISTS users_email_prefix_idx ON users (lower(email) text_pattern_ops);
@@ -0,0 +1,33 @@
+-- Create Friend Request Status
+CREATE TYPE friend_request_status AS ENUM ('pending', 'accepted', 'rejected');
+
+-- Create Friend Requests Table
+CREATE TABLE IF NOT EXISTS friend_requests (
+    id BIGSERIAL PRIMARY KEY,
+    from_user_id BIGINT NOT NULL,
+    to_user_id BIGINT NOT NULL,
+    message VARCHAR(256),
+    status friend_request_status NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (from_user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (to_user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CHECK (from_user_id != to_user_id)
+);
+
+-- only one pending request for each direction
+CREATE UNIQUE INDEX IF NOT EXISTS friend_requests_pending_idx
+    ON friend_requests (from_user_id, to_user_id)
+    WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS friend_requests_to_user_id_idx ON friend_requests (to_user_id, status);
+
+-- Create Contacts Table, every contact is stored in both directions
+CREATE TABLE IF NOT EXISTS contacts (
+    user_id BIGINT NOT NULL,
+    contact_id BIGINT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, contact_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (contact_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,33 @@
+-- Create User Blocks Table
+CREATE TABLE IF NOT EXISTS user_blocks (
+    user_id BIGINT NOT NULL,
+    blocked_id BIGINT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, blocked_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (blocked_id) REFERENCES users(id) ON DELETE CASCADE,
+    CHECK (user_id != blocked_id)
+);
+
+-- Create Report Types
+CREATE TYPE report_reason AS ENUM ('spam', 'harassment', 'inappropriate', 'impersonation', 'other');
+CREATE TYPE report_status AS ENUM ('open', 'resolved', 'dismissed');
+
+-- Create Reports Table
+-- context keeps a snapshot of the reported content, so moderators can still see it after it is deleted
+CREATE TABLE IF NOT EXISTS reports (
+    id BIGSERIAL PRIMARY KEY,
+    reporter_id BIGINT NOT NULL,
+    target_user_id BIGINT NOT NULL,
+    chat_id BIGINT,
+    message_id BIGINT,
+    reason report_reason NOT NULL,
+    description TEXT,
+    context JSONB NOT NULL DEFAULT '{}',
+    status report_status NOT NULL DEFAULT 'open',
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (reporter_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (target_user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS reports_status_idx ON reports (status, id);
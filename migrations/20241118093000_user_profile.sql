@@ -0,0 +1,4 @@
+-- Profile fields editable by the user
+ALTER TABLE users
+    ADD COLUMN bio VARCHAR(256),
+    ADD COLUMN status_text VARCHAR(64);
@@ -0,0 +1,33 @@
+-- Account deletion with a grace period, deleted accounts are anonymized instead of removed
+ALTER TABLE users
+    ADD COLUMN deletion_scheduled_at TIMESTAMPTZ,
+    ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS users_deletion_scheduled_at_idx ON users (deletion_scheduled_at)
+    WHERE deletion_scheduled_at IS NOT NULL AND deleted_at IS NULL;
+
+-- deleting a user row must not wipe the chats and messages other people still see
+ALTER TABLE chats
+    DROP CONSTRAINT IF EXISTS chats_owner_id_fkey,
+    ADD CONSTRAINT chats_owner_id_fkey FOREIGN KEY (owner_id) REFERENCES users(id) ON DELETE RESTRICT;
+
+ALTER TABLE messages
+    DROP CONSTRAINT IF EXISTS messages_user_id_fkey,
+    ADD CONSTRAINT messages_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE RESTRICT;
+
+-- Create Data Export Status
+CREATE TYPE data_export_status AS ENUM ('pending', 'ready', 'failed');
+
+-- Create Data Exports Table
+CREATE TABLE IF NOT EXISTS data_exports (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    status data_export_status NOT NULL DEFAULT 'pending',
+    content JSONB,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS data_exports_user_id_idx ON data_exports (user_id, id);
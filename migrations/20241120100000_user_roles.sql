@@ -0,0 +1,12 @@
+-- System-level role of a user, operators are moderators or admins
+CREATE TYPE user_role AS ENUM ('user', 'moderator', 'admin');
+
+ALTER TABLE users
+    ADD COLUMN role user_role NOT NULL DEFAULT 'user',
+    ADD COLUMN suspended_until TIMESTAMPTZ,
+    ADD COLUMN suspension_reason VARCHAR(256);
+
+-- who processed a report, and when
+ALTER TABLE reports
+    ADD COLUMN handled_by BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    ADD COLUMN handled_at TIMESTAMPTZ;
@@ -0,0 +1,5 @@
+-- A banned account is locked until an operator lifts the ban, suspension_reason is kept for both
+ALTER TABLE users
+    ADD COLUMN banned BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN users.suspension_reason IS 'reason of the suspension or the ban, shown to the user';
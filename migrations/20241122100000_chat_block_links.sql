@@ -0,0 +1,3 @@
+-- the owner of a chat can forbid links in its messages
+ALTER TABLE chats
+    ADD COLUMN block_links BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,4 @@
+-- unsent draft of a member, synced between the devices of the user
+ALTER TABLE chat_members
+    ADD COLUMN draft TEXT,
+    ADD COLUMN draft_updated_at TIMESTAMPTZ;
@@ -0,0 +1,25 @@
+-- Messages pinned in a chat, visible to every member
+-- no foreign key to the partitioned messages table, partitions are detached when a chat is dropped
+CREATE TABLE IF NOT EXISTS pinned_messages (
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    pinned_by BIGINT NOT NULL,
+    pinned_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (chat_id, message_id),
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE,
+    FOREIGN KEY (pinned_by) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- Messages starred by a user, only visible to that user
+CREATE TABLE IF NOT EXISTS starred_messages (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, chat_id, message_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS starred_messages_user_id_idx ON starred_messages (user_id, id);
@@ -0,0 +1,22 @@
+-- whether forwarded copies of my messages link to my account, otherwise only my name is shown
+ALTER TABLE users
+    ADD COLUMN forward_attribution BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Origin of forwarded messages, a forward of a forward keeps the first origin
+CREATE TABLE IF NOT EXISTS message_forwards (
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    origin_user_id BIGINT,
+    -- false if the sender does not allow to link their account
+    attributed BOOLEAN NOT NULL,
+    origin_sender_name VARCHAR(64) NOT NULL,
+    origin_chat_id BIGINT,
+    origin_message_id BIGINT,
+    origin_created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (chat_id, message_id),
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE,
+    FOREIGN KEY (origin_user_id) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (origin_chat_id) REFERENCES chats(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS message_forwards_origin_user_id_idx ON message_forwards (origin_user_id);
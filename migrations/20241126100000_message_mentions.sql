@@ -0,0 +1,14 @@
+-- Mentions of a message, one row per message which mentions anyone
+CREATE TABLE IF NOT EXISTS message_mentions (
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    user_ids BIGINT[] NOT NULL DEFAULT '{}',
+    -- @all, mentions every member of the chat
+    mention_all BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (chat_id, message_id),
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE
+);
+
+-- unread messages which mention the member, reset together with unread_count
+ALTER TABLE chat_members
+    ADD COLUMN unread_mention_count INT NOT NULL DEFAULT 0;
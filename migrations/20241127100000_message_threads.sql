@@ -0,0 +1,16 @@
+-- Replies of a thread point at its root message, a reply can not be the root of another thread
+ALTER TABLE messages
+    ADD COLUMN thread_root_id BIGINT;
+
+CREATE INDEX IF NOT EXISTS messages_thread_root_id_idx ON messages (chat_id, thread_root_id, id)
+    WHERE thread_root_id IS NOT NULL;
+
+-- Reply count and last reply time of every thread, one row per root message with replies
+CREATE TABLE IF NOT EXISTS message_threads (
+    chat_id BIGINT NOT NULL,
+    root_id BIGINT NOT NULL,
+    reply_count INT NOT NULL DEFAULT 0,
+    last_reply_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (chat_id, root_id),
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE
+);
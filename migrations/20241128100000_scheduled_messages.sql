@@ -0,0 +1,21 @@
+-- Messages sent later by the scheduler, kept here so schedules survive restarts
+CREATE TYPE scheduled_message_status AS ENUM ('pending', 'sent', 'cancelled', 'failed');
+
+CREATE TABLE IF NOT EXISTS scheduled_messages (
+    id BIGSERIAL PRIMARY KEY,
+    chat_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    content TEXT NOT NULL,
+    send_at TIMESTAMPTZ NOT NULL,
+    status scheduled_message_status NOT NULL DEFAULT 'pending',
+    -- the message created when it was sent
+    message_id BIGINT,
+    failure_reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS scheduled_messages_pending_idx ON scheduled_messages (send_at)
+    WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS scheduled_messages_user_id_idx ON scheduled_messages (user_id, send_at);
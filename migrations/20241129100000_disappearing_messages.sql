@@ -0,0 +1,37 @@
+-- Messages sent to the chat are deleted this long after they were sent, NULL keeps them
+ALTER TABLE chats
+    ADD COLUMN message_ttl_seconds INT;
+
+ALTER TABLE messages
+    ADD COLUMN expires_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS messages_expires_at_idx ON messages (expires_at)
+    WHERE expires_at IS NOT NULL;
+
+CREATE OR REPLACE FUNCTION set_message_expires_at()
+    RETURNS TRIGGER
+    AS $$
+BEGIN
+    SELECT COALESCE(NEW.created_at, now()) + make_interval(secs => message_ttl_seconds)
+    INTO NEW.expires_at
+    FROM chats
+    WHERE id = NEW.chat_id;
+    RETURN NEW;
+END;
+    $$
+LANGUAGE plpgsql;
+
+CREATE TRIGGER message_expires_at_trigger
+    BEFORE INSERT
+    ON messages
+    FOR EACH ROW
+    EXECUTE FUNCTION set_message_expires_at();
+
+-- members are told when the timer changes
+DROP TRIGGER IF EXISTS chat_change_trigger ON chats;
+
+CREATE TRIGGER chat_change_trigger
+    AFTER INSERT OR UPDATE OF name, owner_id, message_ttl_seconds OR DELETE
+    ON chats
+    FOR EACH ROW
+    EXECUTE FUNCTION notify_chat_change();
@@ -0,0 +1,38 @@
+ALTER TYPE message_type ADD VALUE IF NOT EXISTS 'poll';
+
+-- A poll is a message of type poll, its content is the question
+-- no foreign key to the partitioned messages table, partitions are detached when a chat is dropped
+CREATE TABLE IF NOT EXISTS polls (
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    created_by BIGINT NOT NULL,
+    multi_choice BOOLEAN NOT NULL DEFAULT FALSE,
+    -- voters are hidden from everyone
+    anonymous BOOLEAN NOT NULL DEFAULT FALSE,
+    closes_at TIMESTAMPTZ,
+    closed_at TIMESTAMPTZ,
+    PRIMARY KEY (chat_id, message_id),
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS poll_options (
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    -- position of the option in the poll, starting at 0
+    option_id INT NOT NULL,
+    text TEXT NOT NULL,
+    PRIMARY KEY (chat_id, message_id, option_id),
+    FOREIGN KEY (chat_id, message_id) REFERENCES polls(chat_id, message_id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS poll_votes (
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    option_id INT NOT NULL,
+    user_id BIGINT NOT NULL,
+    voted_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (chat_id, message_id, option_id, user_id),
+    FOREIGN KEY (chat_id, message_id, option_id) REFERENCES poll_options(chat_id, message_id, option_id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,13 @@
+-- Preview of the first link in a text message, added after the message was sent
+CREATE TABLE IF NOT EXISTS message_link_previews (
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT,
+    description TEXT,
+    image_url TEXT,
+    site_name TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (chat_id, message_id),
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE
+);
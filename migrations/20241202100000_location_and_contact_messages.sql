@@ -0,0 +1,28 @@
+ALTER TYPE message_type ADD VALUE IF NOT EXISTS 'location';
+ALTER TYPE message_type ADD VALUE IF NOT EXISTS 'contact';
+
+-- The message content is a readable summary, the data of the location or card is kept here
+-- no foreign key to the partitioned messages table, partitions are detached when a chat is dropped
+CREATE TABLE IF NOT EXISTS message_locations (
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    latitude DOUBLE PRECISION NOT NULL,
+    longitude DOUBLE PRECISION NOT NULL,
+    label TEXT,
+    -- a live location is updated by the sender until then
+    live_until TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (chat_id, message_id),
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS message_contacts (
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    -- NULL once the shared account is deleted
+    user_id BIGINT,
+    -- name of the shared user when the card was sent
+    display_name TEXT NOT NULL,
+    PRIMARY KEY (chat_id, message_id),
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE
+);
@@ -0,0 +1,19 @@
+ALTER TYPE message_type ADD VALUE IF NOT EXISTS 'system';
+
+CREATE TYPE system_action AS ENUM ('group_created', 'group_renamed', 'member_left', 'owner_changed');
+
+-- The message content is an english fallback, clients localize the event from here
+-- no foreign key to the partitioned messages table, partitions are detached when a chat is dropped
+CREATE TABLE IF NOT EXISTS message_system_events (
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    -- the user who made the change, also the sender of the message
+    actor_id BIGINT NOT NULL,
+    action system_action NOT NULL,
+    -- users the change was about, e.g. the members of a new group or the new owner
+    target_ids BIGINT[] NOT NULL DEFAULT '{}',
+    -- the new name of a renamed group
+    detail TEXT,
+    PRIMARY KEY (chat_id, message_id),
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE
+);
@@ -0,0 +1,41 @@
+ALTER TYPE message_type ADD VALUE IF NOT EXISTS 'sticker';
+
+-- server-managed packs have no owner, every user has at most one pack of custom stickers
+CREATE TABLE IF NOT EXISTS sticker_packs (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR(64) NOT NULL,
+    owner_id BIGINT REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS sticker_packs_owner_id_index ON sticker_packs(owner_id);
+
+CREATE TABLE IF NOT EXISTS stickers (
+    id BIGSERIAL PRIMARY KEY,
+    pack_id BIGINT NOT NULL REFERENCES sticker_packs(id) ON DELETE CASCADE,
+    -- url of the image uploaded to the file storage
+    image_url TEXT NOT NULL,
+    -- the emoji the sticker stands for, shown in chat lists
+    emoji TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS stickers_pack_id_index ON stickers(pack_id, id);
+
+-- packs of other users added to my sticker panel, server-managed packs are always there
+CREATE TABLE IF NOT EXISTS user_sticker_packs (
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    pack_id BIGINT NOT NULL REFERENCES sticker_packs(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, pack_id)
+);
+
+-- a sticker message only references the sticker, the image is not copied
+-- no foreign key to the partitioned messages table, partitions are detached when a chat is dropped
+CREATE TABLE IF NOT EXISTS message_stickers (
+    chat_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    sticker_id BIGINT NOT NULL REFERENCES stickers(id) ON DELETE CASCADE,
+    PRIMARY KEY (chat_id, message_id),
+    FOREIGN KEY (chat_id) REFERENCES chats(id) ON DELETE CASCADE
+);
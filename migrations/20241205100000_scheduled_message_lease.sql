@@ -0,0 +1,6 @@
+-- A scheduler claims a due message while it sends it, the claim expires so a message
+-- claimed by a scheduler which crashed is sent by the next one
+ALTER TYPE scheduled_message_status ADD VALUE IF NOT EXISTS 'sending' AFTER 'pending';
+
+ALTER TABLE scheduled_messages
+    ADD COLUMN claimed_at TIMESTAMPTZ;
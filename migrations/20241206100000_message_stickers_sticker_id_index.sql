@@ -0,0 +1,2 @@
+-- a custom pack is visible to the members of the chats its stickers were sent in
+CREATE INDEX IF NOT EXISTS message_stickers_sticker_id_index ON message_stickers(sticker_id, chat_id);
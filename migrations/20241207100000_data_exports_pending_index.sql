@@ -0,0 +1,9 @@
+-- at most one pending export per user, so concurrent requests do not build the same data twice
+UPDATE data_exports d
+SET status = 'failed', finished_at = now()
+WHERE status = 'pending' AND EXISTS (
+    SELECT 1 FROM data_exports o WHERE o.user_id = d.user_id AND o.status = 'pending' AND o.id > d.id
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS data_exports_pending_user_id_idx ON data_exports (user_id)
+    WHERE status = 'pending';